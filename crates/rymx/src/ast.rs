@@ -1,25 +1,131 @@
+use crate::span::Spanned;
 use itertools::Itertools;
 use std::{
-    collections::HashMap,
+    collections::BTreeMap,
     fmt::{Debug, Display},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Module {
-    pub name: String,
-    pub constants: Vec<(String, Type, Expr)>,
-    pub types: Vec<(String, Type)>,
+    pub name: Spanned<String>,
+    pub visibility: Visibility,
+    pub doc: Option<DocComment>,
+    /// File of a module declared with `module name;` or imported with `import("name.rym")`,
+    /// relative to the directory of the file it is declared in
+    pub file: Option<Spanned<String>>,
+    pub constants: Vec<Constant>,
+    pub types: Vec<(
+        Spanned<String>,
+        Spanned<Type>,
+        Option<DocComment>,
+        Visibility,
+    )>,
+    pub impls: Vec<Impl>,
+    pub uses: Vec<Use>,
     pub sub_modules: Vec<Module>,
+    /// Only evaluated by the test runner
+    pub tests: Vec<Test>,
+}
+
+impl Module {
+    pub fn new(name: Spanned<String>) -> Self {
+        Self {
+            name,
+            visibility: Visibility::Private,
+            doc: None,
+            file: None,
+            constants: vec![],
+            types: vec![],
+            impls: vec![],
+            uses: vec![],
+            sub_modules: vec![],
+            tests: vec![],
+        }
+    }
+}
+
+/// A constant or function of a module or an `impl` block, with its type
+pub type Constant = (
+    Spanned<String>,
+    Spanned<Type>,
+    Spanned<Expr>,
+    Option<DocComment>,
+    Visibility,
+);
+
+/// Where the items of a module and the members of an `impl` block can be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// Only inside of the module it is declared in
+    Private,
+    /// `pub(super)`, inside of the parent module
+    Super,
+    /// `pub(package)`, inside of the whole package
+    Package,
+    /// `pub`, everywhere
+    Public,
+}
+
+/// `use super.module.{name, other}` or `use module.name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Use {
+    /// Starts with `super`, `package` or a name in scope
+    pub path: Vec<Spanned<String>>,
+    pub items: Vec<Spanned<String>>,
+}
+
+/// `test "name" { assert_eq(1 + 1, 2); }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Test {
+    pub name: Spanned<String>,
+    pub body: Spanned<Expr>,
+    pub doc: Option<DocComment>,
+}
+
+/// `impl[T] Type[T] { const NAME = ..; fn method(self: Self) => .. }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Impl {
+    /// Shared by the associated functions
+    pub generics: Vec<GenericParam>,
+    pub typ: Spanned<Type>,
+    /// Associated constants and functions
+    pub constants: Vec<Constant>,
+    pub doc: Option<DocComment>,
+}
+
+impl Impl {
+    /// Name of the implemented type, without generic arguments
+    pub fn type_name(&self) -> Option<String> {
+        match &self.typ.val {
+            Type::Path(path) => Some(path.to_string()),
+            Type::Generic(typ, _) => match &typ.val {
+                Type::Path(path) => Some(path.to_string()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
-    Expr(Expr),
+    Expr(Spanned<Expr>),
     Function(Function),
-    Type(String, Type),
-    Variable(VariableKind, String, Type, Expr),
+    Type(Spanned<String>, Spanned<Type>, Option<DocComment>),
+    Variable(
+        VariableKind,
+        Spanned<String>,
+        Spanned<Type>,
+        Spanned<Expr>,
+        Option<DocComment>,
+    ),
+    /// Placeholder for a statement that failed to parse, the error was already reported
+    Error,
 }
 
+/// The text of the `///` comments in front of a definition, without the leading slashes
+pub type DocComment = String;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VariableKind {
     Const,
@@ -42,33 +148,89 @@ pub enum Expr {
     // Value creation
     Unit,
     Literal(Literal),
-    Array(Vec<Expr>),
-    ArrayWithRepeat(Box<Expr>, Box<Expr>),
-    Struct(Path, Vec<(String, Expr)>),
-    Function(Function),
+    Array(Vec<Spanned<Expr>>),
+    ArrayWithRepeat(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    /// `(89, "testing")` or with named fields `(number: 89, string: "testing")`
+    Tuple(Vec<(Option<Spanned<String>>, Spanned<Expr>)>),
+    /// `Point { x: 1, y }` or with generic arguments `Box[int] { value: 1 }`
+    Struct(
+        Path,
+        Vec<Spanned<Type>>,
+        Vec<(Spanned<String>, Spanned<Expr>)>,
+    ),
+    /// Boxed, because it is by far the largest expression
+    Function(Box<Function>),
+    /// Module loaded from a file with `import("file.rym")`
+    Module(Box<Module>),
 
     // Value modification
-    Unary(UnaryOp, Box<Expr>),
-    Binary(BinaryOp, Box<Expr>, Box<Expr>),
-    Call(Box<Expr>, Vec<Expr>),
+    Unary(UnaryOp, Box<Spanned<Expr>>),
+    Binary(BinaryOp, Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    /// `func(positional, named: value)`
+    Call(
+        Box<Spanned<Expr>>,
+        Vec<Spanned<Expr>>,
+        Vec<(Spanned<String>, Spanned<Expr>)>,
+    ),
 
     // Value access
     Ident(String),
-    Subscript(Box<Expr>, Box<Expr>),
-    FieldAccess(Box<Expr>, String),
+    Subscript(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    FieldAccess(Box<Spanned<Expr>>, String),
+    /// `id[int]` or `List[int].new`, a generic function or type with explicit generic arguments
+    Generic(Box<Spanned<Expr>>, Vec<Spanned<Type>>),
 
     // Control flow
     IfElse(
         /// Condition
-        Box<Expr>,
+        Box<Spanned<Expr>>,
         /// Then branch
-        Box<Expr>,
+        Box<Spanned<Expr>>,
         /// Else branch
-        Box<Expr>,
+        Box<Spanned<Expr>>,
     ),
-    Block(Vec<Stmt>),
-    Break(Box<Expr>),
-    Return(Box<Expr>),
+    Block(
+        Vec<Spanned<Stmt>>,
+        /// The value of the block, an expression without a semicolon at the end
+        Option<Box<Spanned<Expr>>>,
+    ),
+    Match(Box<Spanned<Expr>>, Vec<MatchArm>),
+    Loop(Box<Spanned<Expr>>),
+    While(
+        /// Condition
+        Box<Spanned<Expr>>,
+        /// Body
+        Box<Spanned<Expr>>,
+    ),
+    For(
+        Spanned<Pattern>,
+        /// Iterator
+        Box<Spanned<Expr>>,
+        /// Body
+        Box<Spanned<Expr>>,
+    ),
+    /// A loop or block with a label, which `break :label` and `continue :label` can refer to
+    Label(Spanned<String>, Box<Spanned<Expr>>),
+    Break(Option<Spanned<String>>, Box<Spanned<Expr>>),
+    Continue(Option<Spanned<String>>),
+    Return(Box<Spanned<Expr>>),
+
+    /// Placeholder for an expression that failed to parse, the error was already reported
+    Error,
+}
+
+impl Expr {
+    /// Expressions ending with a block don't need a semicolon to be a statement
+    pub fn ends_with_block(&self) -> bool {
+        match self {
+            Expr::Block(..) => true,
+            Expr::Loop(body)
+            | Expr::While(_, body)
+            | Expr::For(_, _, body)
+            | Expr::Label(_, body) => body.val.ends_with_block(),
+            _ => false,
+        }
+    }
 }
 
 impl std::fmt::Debug for Expr {
@@ -82,8 +244,15 @@ impl std::fmt::Debug for Expr {
                 .field(arg0)
                 .field(arg1)
                 .finish(),
-            Self::Struct(arg0, arg1) => f.debug_tuple("Struct").field(arg0).field(arg1).finish(),
+            Self::Tuple(arg0) => f.debug_tuple("Tuple").field(arg0).finish(),
+            Self::Struct(arg0, arg1, arg2) => f
+                .debug_tuple("Struct")
+                .field(arg0)
+                .field(arg1)
+                .field(arg2)
+                .finish(),
             Self::Function(arg0) => f.write_fmt(format_args!("{arg0:#?}")),
+            Self::Module(arg0) => f.write_fmt(format_args!("{arg0:#?}")),
 
             Self::Unary(arg0, arg1) => f.debug_tuple(&arg0.to_string()).field(arg1).finish(),
             Self::Binary(arg0, arg1, arg2) => f
@@ -91,7 +260,12 @@ impl std::fmt::Debug for Expr {
                 .field(arg1)
                 .field(arg2)
                 .finish(),
-            Self::Call(arg0, arg1) => f.debug_tuple("Call").field(arg0).field(arg1).finish(),
+            Self::Call(arg0, arg1, arg2) => f
+                .debug_tuple("Call")
+                .field(arg0)
+                .field(arg1)
+                .field(arg2)
+                .finish(),
 
             Self::Subscript(arg0, arg1) => {
                 f.debug_tuple("Subscript").field(arg0).field(arg1).finish()
@@ -101,6 +275,7 @@ impl std::fmt::Debug for Expr {
                 .field(arg0)
                 .field(arg1)
                 .finish(),
+            Self::Generic(arg0, arg1) => f.debug_tuple("Generic").field(arg0).field(arg1).finish(),
             Self::Ident(arg0) => f.write_fmt(format_args!("Ident({arg0:?})")),
 
             Self::IfElse(arg0, arg1, arg2) => f
@@ -109,27 +284,89 @@ impl std::fmt::Debug for Expr {
                 .field(arg1)
                 .field(arg2)
                 .finish(),
-            Self::Block(arg0) => f.debug_tuple("Block").field(arg0).finish(),
-            Self::Break(arg0) => f.debug_tuple("Break").field(arg0).finish(),
+            Self::Block(arg0, arg1) => f.debug_tuple("Block").field(arg0).field(arg1).finish(),
+            Self::Match(arg0, arg1) => f.debug_tuple("Match").field(arg0).field(arg1).finish(),
+            Self::Loop(arg0) => f.debug_tuple("Loop").field(arg0).finish(),
+            Self::While(arg0, arg1) => f.debug_tuple("While").field(arg0).field(arg1).finish(),
+            Self::For(arg0, arg1, arg2) => f
+                .debug_tuple("For")
+                .field(arg0)
+                .field(arg1)
+                .field(arg2)
+                .finish(),
+            Self::Label(arg0, arg1) => f.debug_tuple("Label").field(arg0).field(arg1).finish(),
+            Self::Break(arg0, arg1) => f.debug_tuple("Break").field(arg0).field(arg1).finish(),
+            Self::Continue(arg0) => f.debug_tuple("Continue").field(arg0).finish(),
             Self::Return(arg0) => f.debug_tuple("Return").field(arg0).finish(),
+
+            Self::Error => f.write_str("Error"),
+        }
+    }
+}
+
+/// An arm of a match expression with its pattern, guard and body
+pub type MatchArm = (Spanned<Pattern>, Option<Spanned<Expr>>, Spanned<Expr>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `_`, matches every value
+    Wildcard,
+    Literal(Literal),
+    /// Matches every value and binds it to the name
+    Binding(String),
+    /// `pattern @ name`, binds the value if the pattern matches
+    At(Box<Spanned<Pattern>>, Spanned<String>),
+    /// `pattern | pattern`, matches if any of the alternatives does
+    Or(Vec<Spanned<Pattern>>),
+    Tuple(Vec<Spanned<Pattern>>),
+    /// `Path { field, field = pattern }`, fields which are not listed are ignored
+    Struct(Path, Vec<(Spanned<String>, Spanned<Pattern>)>),
+    /// `Enum.Variant` or `Enum.Variant(pattern)`
+    EnumVariant(Path, Option<Box<Spanned<Pattern>>>),
+}
+
+impl Pattern {
+    /// All names bound by this pattern, alternatives of an or-pattern bind the same names
+    pub fn bindings(pattern: &Spanned<Pattern>) -> Vec<Spanned<String>> {
+        match &pattern.val {
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::EnumVariant(_, None) => vec![],
+            Pattern::Binding(name) => vec![Spanned::new(name.clone(), pattern.span)],
+            Pattern::At(inner, name) => {
+                let mut bindings = Pattern::bindings(inner);
+                bindings.push(name.clone());
+                bindings
+            }
+            Pattern::Or(alternatives) => alternatives
+                .first()
+                .map(Pattern::bindings)
+                .unwrap_or_default(),
+            Pattern::Tuple(items) => items.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::Struct(_, fields) => fields
+                .iter()
+                .flat_map(|(_, pattern)| Pattern::bindings(pattern))
+                .collect(),
+            Pattern::EnumVariant(_, Some(inner)) => Pattern::bindings(inner),
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Function {
-    pub params: Vec<(String, Type)>,
-    pub named_params: HashMap<String, (Type, Expr)>,
-    pub return_type: Type,
-    pub body: Box<Expr>,
+    pub generics: Vec<GenericParam>,
+    pub params: Vec<Spanned<(String, Spanned<Type>)>>,
+    pub named_params: BTreeMap<String, (Spanned<Type>, Spanned<Expr>)>,
+    pub return_type: Spanned<Type>,
+    pub body: Box<Spanned<Expr>>,
 }
 
 impl PartialEq for Function {
     fn eq(&self, other: &Self) -> bool {
-        self.params
-            .iter()
-            .zip(other.params.iter())
-            .all(|((_, typ0), (_, typ1))| typ0 == typ1)
+        self.generics == other.generics
+            && self
+                .params
+                .iter()
+                .zip(other.params.iter())
+                .all(|(param0, param1)| param0.val.1 == param1.val.1)
             && self.named_params == other.named_params
             && self.return_type == other.return_type
             && self.body == other.body
@@ -139,10 +376,11 @@ impl PartialEq for Function {
 impl Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "fn({}) {}",
+            "fn{}({}) {}",
+            generics_to_string(&self.generics),
             self.params
                 .iter()
-                .map(|(_, typ)| format!("{typ}"))
+                .map(|param| format!("{}", param.val.1))
                 .chain(
                     self.named_params
                         .iter()
@@ -154,6 +392,31 @@ impl Display for Function {
     }
 }
 
+/// `T` or with a bound `T: int`, in `fn id[T](..)`, `struct[T] { .. }` or `impl[T]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenericParam {
+    pub name: Spanned<String>,
+    /// The arguments have to be of this type, or of one of its variants for unions
+    pub bound: Option<Spanned<Type>>,
+}
+
+impl Display for GenericParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.bound {
+            Some(bound) => write!(f, "{}: {bound}", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// `[T, U: int]`, or nothing without generic parameters
+fn generics_to_string(generics: &[GenericParam]) -> String {
+    if generics.is_empty() {
+        return String::new();
+    }
+    format!("[{}]", generics.iter().join(", "))
+}
+
 // TODO comments
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
@@ -162,16 +425,21 @@ pub enum Type {
     Never,
     Literal(Literal),
     Path(Path),
-    Generic(Box<Type>, Vec<Type>),
+    Generic(Box<Spanned<Type>>, Vec<Spanned<Type>>),
     Function {
-        args: Vec<Type>,
-        named_args: Vec<(String, Type, Literal)>,
-        return_type: Box<Type>,
+        args: Vec<Spanned<Type>>,
+        named_args: Vec<(String, Spanned<Type>, Literal)>,
+        return_type: Box<Spanned<Type>>,
     },
-    Array(ArraySize, Box<Type>),
-    Struct(Vec<(String, Type, Option<Literal>)>),
-    Enum(Vec<(String, Option<Type>)>),
-    Union(Vec<Type>),
+    Array(ArraySize, Box<Spanned<Type>>),
+    /// `(int, String)` or with named fields `(number: int, string: String)`
+    Tuple(Vec<(Option<String>, Spanned<Type>)>),
+    Struct(
+        Vec<GenericParam>,
+        Vec<(String, Spanned<Type>, Option<Literal>, Option<DocComment>)>,
+    ),
+    Enum(Vec<GenericParam>, Vec<(String, Option<Spanned<Type>>)>),
+    Union(Vec<Spanned<Type>>),
 }
 
 impl Display for Type {
@@ -191,7 +459,7 @@ impl Display for Type {
                 f,
                 "fn({}) {return_type}",
                 args.iter()
-                    .map(Type::to_string)
+                    .map(Spanned::to_string)
                     .chain(
                         named_args
                             .iter()
@@ -200,24 +468,39 @@ impl Display for Type {
                     .join(", "),
             ),
             Type::Array(size, typ) => write!(f, "[{size}]{typ}",),
-            Type::Struct(fields) => write!(
+            Type::Tuple(fields) => write!(
+                f,
+                "({}{})",
+                fields
+                    .iter()
+                    .map(|(name, typ)| match name {
+                        Some(name) => format!("{name}: {typ}"),
+                        None => typ.to_string(),
+                    })
+                    .join(", "),
+                // A single type in parentheses is not a tuple
+                if fields.len() == 1 { "," } else { "" }
+            ),
+            Type::Struct(generics, fields) => write!(
                 f,
-                "struct {{{0}{1}{0}}}",
+                "struct{2} {{{0}{1}{0}}}",
                 if fields.is_empty() { "" } else { " " },
                 fields
                     .iter()
-                    .map(|(name, typ, maybe_val)| format!(
+                    .map(|(name, typ, maybe_val, _)| format!(
                         "{name}: {typ}{}",
                         maybe_val
                             .as_ref()
                             .map(|val| " = ".to_string() + &val.to_string())
                             .unwrap_or("".into())
                     ))
-                    .join(", ")
+                    .join(", "),
+                generics_to_string(generics)
             ),
-            Type::Enum(variants) => write!(
+            Type::Enum(generics, variants) => write!(
                 f,
-                "enum {}",
+                "enum{} {}",
+                generics_to_string(generics),
                 variants
                     .iter()
                     .map(|(name, maybe_typ)| format!(
@@ -259,6 +542,16 @@ pub enum UnaryOp {
     Not,
 }
 
+impl UnaryOp {
+    /// How the operator is written in the source code
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "not",
+        }
+    }
+}
+
 impl Display for UnaryOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{self:?}"))
@@ -275,6 +568,10 @@ pub enum BinaryOp {
     Mul,
     /// Division `1 / 2`
     Div,
+    /// Remainder `7 mod 2`
+    Mod,
+    /// Concatenation of strings or arrays `"a" ++ "b"`
+    Concat,
 
     /// Equality `1 == 2`
     Eq,
@@ -289,8 +586,59 @@ pub enum BinaryOp {
     /// Less than or equal `1 >= 2`
     GreaterThanEq,
 
+    /// Logical and `true and false`, only evaluates the right side if the left is true
+    And,
+    /// Logical or `true or false`, only evaluates the right side if the left is false
+    Or,
+
     /// Assignment `left = right`
     Assign,
+    /// Addition assignment `left += right`
+    AddAssign,
+    /// Subtraction assignment `left -= right`
+    SubAssign,
+    /// Multiplication assignment `left *= right`
+    MulAssign,
+    /// Division assignment `left /= right`
+    DivAssign,
+}
+
+impl BinaryOp {
+    /// How the operator is written in the source code
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "mod",
+            BinaryOp::Concat => "++",
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::LessThan => "<",
+            BinaryOp::LessThanEq => "<=",
+            BinaryOp::GreaterThan => ">",
+            BinaryOp::GreaterThanEq => ">=",
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+            BinaryOp::Assign => "=",
+            BinaryOp::AddAssign => "+=",
+            BinaryOp::SubAssign => "-=",
+            BinaryOp::MulAssign => "*=",
+            BinaryOp::DivAssign => "/=",
+        }
+    }
+
+    /// The operator applied before assigning, `Add` for `+=`
+    pub fn compound_op(self) -> Option<BinaryOp> {
+        match self {
+            BinaryOp::AddAssign => Some(BinaryOp::Add),
+            BinaryOp::SubAssign => Some(BinaryOp::Sub),
+            BinaryOp::MulAssign => Some(BinaryOp::Mul),
+            BinaryOp::DivAssign => Some(BinaryOp::Div),
+            _ => None,
+        }
+    }
 }
 
 impl Display for BinaryOp {
@@ -325,8 +673,10 @@ impl Path {
 #[derive(Clone, PartialEq)]
 pub enum Literal {
     Bool(bool),
-    Int(i64),
-    Float(f64),
+    /// Never negative, `-1` is the negation of the literal `1`
+    Int(u64, Option<NumberSuffix>),
+    Float(f64, Option<NumberSuffix>),
+    Char(char),
     String(String),
 }
 
@@ -334,8 +684,11 @@ impl std::fmt::Debug for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Bool(arg0) => f.write_fmt(format_args!("Bool: {arg0}")),
-            Self::Int(arg0) => f.write_fmt(format_args!("Int: {arg0}")),
-            Self::Float(arg0) => f.write_fmt(format_args!("Float: {arg0}")),
+            Self::Int(arg0, None) => f.write_fmt(format_args!("Int: {arg0}")),
+            Self::Int(arg0, Some(arg1)) => f.write_fmt(format_args!("Int: {arg0}{arg1}")),
+            Self::Float(arg0, None) => f.write_fmt(format_args!("Float: {arg0}")),
+            Self::Float(arg0, Some(arg1)) => f.write_fmt(format_args!("Float: {arg0}{arg1}")),
+            Self::Char(arg0) => f.write_fmt(format_args!("Char: {arg0:?}")),
             Self::String(arg0) => f.write_fmt(format_args!("String: {arg0:?}")),
         }
     }
@@ -345,9 +698,66 @@ impl std::fmt::Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&match self {
             Literal::Bool(inner) => inner.to_string(),
-            Literal::Int(inner) => inner.to_string(),
-            Literal::Float(inner) => inner.to_string(),
+            Literal::Int(inner, None) => inner.to_string(),
+            Literal::Int(inner, Some(suffix)) => format!("{inner}{suffix}"),
+            Literal::Float(inner, None) => inner.to_string(),
+            Literal::Float(inner, Some(suffix)) => format!("{inner}{suffix}"),
+            Literal::Char(inner) => inner.to_string(),
             Literal::String(inner) => inner.to_string(),
         })
     }
 }
+
+/// The explicit type of a number literal, e.g. the `u8` in `255u8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberSuffix {
+    Int(u8),
+    ISize,
+    UInt(u8),
+    USize,
+    Float(u8),
+}
+
+impl NumberSuffix {
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        Some(match suffix {
+            "i8" => Self::Int(8),
+            "i16" => Self::Int(16),
+            "i32" => Self::Int(32),
+            "i64" => Self::Int(64),
+            "isize" => Self::ISize,
+            "u8" => Self::UInt(8),
+            "u16" => Self::UInt(16),
+            "u32" => Self::UInt(32),
+            "u64" => Self::UInt(64),
+            "usize" => Self::USize,
+            "f32" => Self::Float(32),
+            "f64" => Self::Float(64),
+            _ => return None,
+        })
+    }
+
+    /// Smallest and largest value of an integer with the suffix, or of an `int` without one
+    pub fn int_range(suffix: Option<Self>) -> (i128, i128) {
+        match suffix {
+            None => (i64::MIN.into(), i64::MAX.into()),
+            Some(NumberSuffix::Int(size)) => (-(1 << (size - 1)), (1 << (size - 1)) - 1),
+            Some(NumberSuffix::ISize) => (isize::MIN as i128, isize::MAX as i128),
+            Some(NumberSuffix::UInt(size)) => (0, (1 << size) - 1),
+            Some(NumberSuffix::USize) => (0, usize::MAX as i128),
+            Some(NumberSuffix::Float(_)) => unreachable!("Floats are not integers"),
+        }
+    }
+}
+
+impl Display for NumberSuffix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberSuffix::Int(size) => write!(f, "i{size}"),
+            NumberSuffix::ISize => write!(f, "isize"),
+            NumberSuffix::UInt(size) => write!(f, "u{size}"),
+            NumberSuffix::USize => write!(f, "usize"),
+            NumberSuffix::Float(size) => write!(f, "f{size}"),
+        }
+    }
+}
@@ -63,16 +63,16 @@ mod test {
                 Module {
                     name: "ints".into(),
                     constants: vec![
-                        ("one".into(), Expr::Literal(Literal::Int(1))),
-                        ("two".into(), Expr::Literal(Literal::Int(2))),
+                        ("one".into(), Expr::Literal(Literal::Int(1, None))),
+                        ("two".into(), Expr::Literal(Literal::Int(2, None))),
                     ],
                     children: vec![],
                 },
                 Module {
                     name: "floats".into(),
                     constants: vec![
-                        ("one".into(), Expr::Literal(Literal::Float(1.0))),
-                        ("two".into(), Expr::Literal(Literal::Float(2.0))),
+                        ("one".into(), Expr::Literal(Literal::Float(1.0, None))),
+                        ("two".into(), Expr::Literal(Literal::Float(2.0, None))),
                     ],
                     children: vec![],
                 },
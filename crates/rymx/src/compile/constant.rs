@@ -8,6 +8,7 @@ pub enum Constant {
     Bool(bool),
     Int(i64),
     Float(f64),
+    Char(char),
     Array(Vec<Constant>),
     String(String),
 }
@@ -19,6 +20,7 @@ impl Display for Constant {
             Constant::Bool(inner) => write!(f, "{inner}"),
             Constant::Int(inner) => write!(f, "{inner}"),
             Constant::Float(inner) => write!(f, "{inner}"),
+            Constant::Char(inner) => write!(f, "{inner}"),
             Constant::Array(inner) => write!(f, "[{}]", inner.iter().join(", ")),
             Constant::String(inner) => write!(f, "{inner}"),
         }
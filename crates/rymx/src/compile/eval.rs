@@ -1,6 +1,6 @@
 use super::{
     module::{FunctionPrototype, ModulePrototype},
-    Constant,
+    Constant, Type,
 };
 use crate::ast;
 use std::cell::RefCell;
@@ -17,8 +17,16 @@ impl ConstEvaluator {
             ast_module,
         };
 
-        for (name, _, expr) in &this.ast_module.constants {
-            this.eval_expr(expr);
+        for (name, _, expr, _, _) in &this.ast_module.constants {
+            let typ = match &expr.val {
+                ast::Expr::Literal(lit) => Self::literal_to_type(lit),
+                _ => Type::Unknown,
+            };
+            let value = this.eval_expr(&expr.val);
+            this.prototype
+                .borrow_mut()
+                .constants
+                .insert(name.val.clone(), (typ, value));
         }
 
         // assert_eq!(this.typed_modules.len(), 1);
@@ -31,23 +39,34 @@ impl ConstEvaluator {
             ast::Expr::Literal(lit) => Self::literal_to_constant(lit),
             ast::Expr::Array(_) => todo!(),
             ast::Expr::ArrayWithRepeat(_, _) => todo!(),
-            ast::Expr::Struct(name, fields) => todo!(),
+            ast::Expr::Tuple(_) => todo!(),
+            ast::Expr::Struct(name, _, fields) => todo!(),
             ast::Expr::Function(_) => todo!(),
+            ast::Expr::Module(_) => todo!(),
 
             ast::Expr::Unary(_, _) => todo!(),
             ast::Expr::Binary(_, _, _) => todo!(),
-            ast::Expr::Call(_, _) => todo!(),
+            ast::Expr::Call(..) => todo!(),
 
             ast::Expr::Ident(name) => self
                 .resolve_ident(name)
                 .expect(&format!("Could not find {name}")),
             ast::Expr::FieldAccess(_, _) => todo!(),
             ast::Expr::Subscript(_, _) => todo!(),
+            ast::Expr::Generic(_, _) => todo!(),
 
             ast::Expr::IfElse(_, _, _) => todo!(),
-            ast::Expr::Block(_) => todo!(),
-            ast::Expr::Break(_) => todo!(),
+            ast::Expr::Block(_, _) => todo!(),
+            ast::Expr::Match(_, _) => todo!(),
+            ast::Expr::Loop(_) => todo!(),
+            ast::Expr::While(_, _) => todo!(),
+            ast::Expr::For(_, _, _) => todo!(),
+            ast::Expr::Label(_, _) => todo!(),
+            ast::Expr::Break(_, _) => todo!(),
+            ast::Expr::Continue(_) => todo!(),
             ast::Expr::Return(_) => todo!(),
+
+            ast::Expr::Error => unreachable!("Modules with syntax errors are not evaluated"),
         }
     }
 
@@ -70,9 +89,24 @@ impl ConstEvaluator {
     fn literal_to_constant(lit: &ast::Literal) -> Constant {
         match lit {
             ast::Literal::Bool(inner) => Constant::Bool(*inner),
-            ast::Literal::Int(inner) => Constant::Int(*inner),
-            ast::Literal::Float(inner) => Constant::Float(*inner),
+            ast::Literal::Int(inner, _) => Constant::Int(*inner as i64),
+            ast::Literal::Float(inner, _) => Constant::Float(*inner),
+            ast::Literal::Char(inner) => Constant::Char(*inner),
             ast::Literal::String(inner) => Constant::String(inner.clone()),
         }
     }
+
+    fn literal_to_type(lit: &ast::Literal) -> Type {
+        match lit {
+            ast::Literal::Bool(_) => Type::Bool,
+            ast::Literal::Int(_, None) => Type::IntLiteral,
+            ast::Literal::Float(_, None) => Type::FloatLiteral,
+            ast::Literal::Int(_, Some(suffix)) | ast::Literal::Float(_, Some(suffix)) => {
+                Type::from(*suffix)
+            }
+            ast::Literal::Char(_) => Type::Char,
+            // TODO Add a string type
+            ast::Literal::String(_) => Type::Unknown,
+        }
+    }
 }
@@ -1,4 +1,7 @@
-use crate::ast::{Expr, Function};
+use crate::{
+    ast::{Expr, Function},
+    span::Spanned,
+};
 use std::collections::HashMap;
 
 /// Inspired by: https://github.com/RustPython/RustPython/blob/main/compiler/codegen/src/symboltable.rs
@@ -60,14 +63,14 @@ impl SymbolTableBuilder {
         let table = self.tabels.pop().unwrap();
         self.tabels.last_mut().unwrap().sub_tables.push(table);
     }
-    pub fn scan_expressions(&mut self, exprs: &[Expr]) {
+    pub fn scan_expressions(&mut self, exprs: &[Spanned<Expr>]) {
         for expr in exprs {
             self.scan_expression(expr);
         }
     }
 
-    pub fn scan_expression(&mut self, expr: &Expr) {
-        match expr {
+    pub fn scan_expression(&mut self, expr: &Spanned<Expr>) {
+        match &expr.val {
             Expr::Ident(_) => {
                 // TODO
             }
@@ -81,9 +84,12 @@ impl SymbolTableBuilder {
                 self.scan_expression(lhs);
                 self.scan_expression(rhs);
             }
-            Expr::Call(lhs, args) => {
+            Expr::Call(lhs, args, named_args) => {
                 self.scan_expression(lhs);
                 self.scan_expressions(args);
+                for (_, arg) in named_args {
+                    self.scan_expression(arg);
+                }
             }
             Expr::IfElse(test, then, other) => {
                 self.scan_expression(test);
@@ -91,7 +97,8 @@ impl SymbolTableBuilder {
                 self.scan_expression(other);
             }
             // Expr::Block(stmts) => self.scan_statements(stmts),
-            Expr::Break(rhs) => self.scan_expression(rhs),
+            Expr::Label(_, inner) => self.scan_expression(inner),
+            Expr::Break(_, rhs) => self.scan_expression(rhs),
             Expr::Return(rhs) => self.scan_expression(rhs),
             // Expr::Var(_, name, rhs) => {
             // 	self.scan_expression(rhs);
@@ -105,8 +112,8 @@ impl SymbolTableBuilder {
         let Function { params, body, .. } = func;
         self.enter_scope("<function>", SymbolTableType::Function);
 
-        for (name, _typ) in params {
-            self.register_name(name);
+        for param in params {
+            self.register_name(&param.val.0);
         }
         self.scan_expression(body);
 
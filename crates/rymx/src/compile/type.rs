@@ -1,4 +1,5 @@
 use super::Constant;
+use crate::ast::NumberSuffix;
 use itertools::Itertools;
 use std::fmt::{Debug, Display};
 
@@ -8,6 +9,7 @@ pub enum Type {
     Never,
     Unknown,
     Bool,
+    Char,
     IntLiteral,
     Int(u8),
     ISize,
@@ -47,6 +49,18 @@ impl Type {
     }
 }
 
+impl From<NumberSuffix> for Type {
+    fn from(suffix: NumberSuffix) -> Self {
+        match suffix {
+            NumberSuffix::Int(size) => Type::Int(size),
+            NumberSuffix::ISize => Type::ISize,
+            NumberSuffix::UInt(size) => Type::UInt(size),
+            NumberSuffix::USize => Type::USize,
+            NumberSuffix::Float(size) => Type::Float(size),
+        }
+    }
+}
+
 impl Debug for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.to_string().as_str())
@@ -60,6 +74,7 @@ impl Display for Type {
             Type::Never => write!(f, "never"),
             Type::Unknown => write!(f, "<unkown>"),
             Type::Bool => write!(f, "bool"),
+            Type::Char => write!(f, "char"),
             Type::IntLiteral => write!(f, "<int_lit>"),
             Type::Int(size) => write!(f, "i{size}"),
             Type::ISize => write!(f, "isize"),
@@ -105,6 +120,8 @@ fn display_types() {
     assert_eq!(&Type::Float(11).to_string(), "f11");
     // ... and so on
 
+    assert_eq!(&Type::Char.to_string(), "char");
+
     assert_eq!(&Type::Int(1).to_string(), "i1");
     assert_eq!(&Type::Int(2).to_string(), "i2");
     assert_eq!(&Type::Int(8).to_string(), "i8");
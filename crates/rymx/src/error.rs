@@ -2,4 +2,4 @@ mod diagnostic;
 mod emitter;
 
 pub use diagnostic::{Diagnostic, Level};
-pub use emitter::{AriadneEmitter, SourceId};
+pub use emitter::{AriadneEmitter, SourceId, SourceMap};
@@ -9,6 +9,7 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::io;
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 // pub trait Emitter {
 //     fn emit_diagnostic(&mut self, diagnostic: &Diagnostic);
@@ -18,6 +19,8 @@ pub struct AriadneEmitter<W: io::Write> {
     pub source_map: SourceMap,
     receiver: Receiver<Diagnostic>,
     out: RefCell<W>,
+    /// Whether [`Level::Debug`] diagnostics are emitted
+    debug: bool,
 }
 
 impl<W: io::Write> AriadneEmitter<W> {
@@ -27,23 +30,39 @@ impl<W: io::Write> AriadneEmitter<W> {
             out: RefCell::new(out),
             source_map: SourceMap::new(),
             receiver,
+            debug: true,
         };
         (sender, emitter)
     }
 
+    /// Ignores [`Level::Debug`] diagnostics, like the tokens and the syntax tree of each file
+    pub fn without_debug(self) -> Self {
+        Self {
+            debug: false,
+            ..self
+        }
+    }
+
     pub fn emit(&self, diagnostic: Diagnostic) {
+        if !self.debug && diagnostic.level == Level::Debug {
+            return;
+        }
         type Report<'a> = ariadne::Report<'a, crate::Span>;
+        let source_map = self.source_map.lock();
+        let source_map = &*source_map;
 
         if diagnostic.span.is_none() && diagnostic.level == Level::Debug {
             let mut out = self.out.borrow_mut();
             Report::build(
                 level_to_kind(diagnostic.level),
                 SourceId::INVALID,
-                diagnostic.span.unwrap_or(Span::new(0, 0)).start,
+                source_map
+                    .char_span(diagnostic.span.unwrap_or(Span::new(0, 0)))
+                    .start,
             )
             .with_message(&diagnostic.message)
             .finish()
-            .write(&self.source_map, out.by_ref())
+            .write(source_map, out.by_ref())
             .unwrap();
             for child in &diagnostic.children {
                 write!(out, "{}", child.message).unwrap();
@@ -52,9 +71,10 @@ impl<W: io::Write> AriadneEmitter<W> {
             return;
         };
 
-        fn map_children(
-            children: &[SubDiagnostic],
-        ) -> (Vec<Label<Span>>, Vec<&String>, Vec<&String>) {
+        fn map_children<'a>(
+            children: &'a [SubDiagnostic],
+            source_map: &SourceFiles,
+        ) -> (Vec<Label<Span>>, Vec<&'a String>, Vec<&'a String>) {
             let mut labels = vec![];
             let mut notes = vec![];
             let mut helps = vec![];
@@ -66,10 +86,9 @@ impl<W: io::Write> AriadneEmitter<W> {
                         Level::Help => helps.push(&child.message),
                         Level::Error | Level::Warning => todo!(),
                     }
-                    notes.push(&child.message)
                 }
                 labels.extend(child.spans.iter().map(|span| {
-                    Label::new(*span)
+                    Label::new(source_map.char_span(*span))
                         .with_message(&child.message)
                         .with_color(level_to_color(child.level))
                 }));
@@ -81,7 +100,9 @@ impl<W: io::Write> AriadneEmitter<W> {
         let mut builder = Report::build(
             level_to_kind(diagnostic.level),
             SourceId::INVALID,
-            diagnostic.span.unwrap_or(Span::new(0, 0)).start,
+            source_map
+                .char_span(diagnostic.span.unwrap_or(Span::new(0, 0)))
+                .start,
         )
         .with_message(&diagnostic.message);
 
@@ -89,12 +110,22 @@ impl<W: io::Write> AriadneEmitter<W> {
             && let Some(span) = diagnostic.span
         {
             builder.add_label(
-                Label::new(span)
+                Label::new(source_map.char_span(span))
                     .with_color(level_to_color(diagnostic.level))
                     .with_message(diagnostic.message),
             )
         } else {
-            let (labels, notes, helps) = map_children(&diagnostic.children);
+            let (labels, notes, helps) = map_children(&diagnostic.children, source_map);
+            // Only notes and helps, so the message has to point at the span itself
+            if labels.is_empty()
+                && let Some(span) = diagnostic.span
+            {
+                builder.add_label(
+                    Label::new(source_map.char_span(span))
+                        .with_color(level_to_color(diagnostic.level))
+                        .with_message(&diagnostic.message),
+                )
+            }
             builder.add_labels(labels);
 
             // TODO Properly render multiple notes/helps
@@ -107,10 +138,7 @@ impl<W: io::Write> AriadneEmitter<W> {
         }
 
         let mut out = self.out.borrow_mut();
-        builder
-            .finish()
-            .write(&self.source_map, out.by_ref())
-            .unwrap();
+        builder.finish().write(source_map, out.by_ref()).unwrap();
         writeln!(out).unwrap();
     }
 
@@ -149,57 +177,109 @@ fn level_to_color(level: Level) -> Color {
     }
 }
 
-#[derive(Debug)]
+/// Names and contents of all source files, shared between the emitter and the compiler,
+/// which adds files as it loads modules
+#[derive(Debug, Clone)]
 pub struct SourceMap {
-    map: HashMap<SourceId, (String, Source)>,
-    next_id: SourceId,
-}
-
-impl Cache<SourceId> for &SourceMap {
-    fn fetch(&mut self, id: &SourceId) -> Result<&Source, Box<dyn Debug + '_>> {
-        match self.source(*id) {
-            Some(source) => Ok(source),
-            None => panic!("Internal Error: SourceId '{:?}' does not exist", id),
-        }
-    }
-
-    fn display<'a>(&self, id: &'a SourceId) -> Option<Box<dyn Display + 'a>> {
-        let name = self.name(*id)?;
-        Some(Box::new(name.to_owned()))
-    }
+    files: Arc<Mutex<SourceFiles>>,
 }
 
 impl SourceMap {
     fn new() -> Self {
         Self {
-            map: HashMap::new(),
-            next_id: SourceId::FIRST,
+            files: Arc::new(Mutex::new(SourceFiles {
+                map: HashMap::new(),
+                next_id: SourceId::FIRST,
+            })),
         }
     }
 
-    pub fn add(&mut self, name: impl Into<String>, src: impl Into<Source>) -> SourceId {
-        let id = self.next_id;
-        self.map.insert(id, (name.into(), src.into()));
-        self.next_id.0 += 1;
+    fn lock(&self) -> MutexGuard<'_, SourceFiles> {
+        self.files
+            .lock()
+            .expect("Internal Error: SourceMap is poisoned")
+    }
+
+    pub fn add(&self, name: impl Into<String>, src: impl AsRef<str>) -> SourceId {
+        let mut files = self.lock();
+        let id = files.next_id;
+        let text = src.as_ref().to_string();
+        let source = Source::from(&text);
+        files.map.insert(
+            id,
+            SourceFile {
+                name: name.into(),
+                text,
+                source,
+            },
+        );
+        files.next_id.0 += 1;
         id
     }
 
-    pub fn replace(&mut self, id: SourceId, src: impl Into<Source>) {
-        let Some((_, ref mut source)) = self.map.get_mut(&id) else {
+    pub fn replace(&self, id: SourceId, src: impl AsRef<str>) {
+        let mut files = self.lock();
+        let Some(file) = files.map.get_mut(&id) else {
             panic!("Internal Error: SourceId '{:?}' does not exist", id)
         };
-        *source = src.into();
+        file.text = src.as_ref().to_string();
+        file.source = Source::from(&file.text);
     }
 
-    pub fn source(&self, id: SourceId) -> Option<&Source> {
-        self.map.get(&id).map(|(_, source)| source)
+    pub fn name(&self, id: SourceId) -> Option<String> {
+        self.lock().map.get(&id).map(|file| file.name.clone())
     }
 
-    pub fn name(&self, id: SourceId) -> Option<&String> {
-        self.map.get(&id).map(|(name, _)| name)
+    pub fn text(&self, id: SourceId) -> Option<String> {
+        self.lock().map.get(&id).map(|file| file.text.clone())
     }
 }
 
+#[derive(Debug)]
+struct SourceFiles {
+    map: HashMap<SourceId, SourceFile>,
+    next_id: SourceId,
+}
+
+impl Cache<SourceId> for &SourceFiles {
+    fn fetch(&mut self, id: &SourceId) -> Result<&Source, Box<dyn Debug + '_>> {
+        match self.map.get(id) {
+            Some(file) => Ok(&file.source),
+            None => panic!("Internal Error: SourceId '{:?}' does not exist", id),
+        }
+    }
+
+    fn display<'a>(&self, id: &'a SourceId) -> Option<Box<dyn Display + 'a>> {
+        let name = &self.map.get(id)?.name;
+        Some(Box::new(name.to_owned()))
+    }
+}
+
+impl SourceFiles {
+    /// Spans index bytes, but ariadne expects char indices
+    fn char_span(&self, span: Span) -> Span {
+        let Some(file) = self.map.get(&span.id) else {
+            return span;
+        };
+        let to_char_index = |index: usize| match file.text.get(..index) {
+            Some(prefix) => prefix.chars().count(),
+            None => index,
+        };
+        Span {
+            start: to_char_index(span.start),
+            end: to_char_index(span.end),
+            id: span.id,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SourceFile {
+    name: String,
+    text: String,
+    source: Source,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SourceId(u32);
 
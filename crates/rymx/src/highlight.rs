@@ -0,0 +1,173 @@
+//! Syntax highlighting built directly on the [`tokenizer`], so it always agrees with the language
+
+use crate::tokenize::{tokenizer, Token};
+use owo_colors::OwoColorize;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Colored with ANSI escape codes, for terminals
+    Ansi,
+    /// Wrapped in `<span class="...">`, for docs
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Comment,
+    Keyword,
+    Literal,
+    Operator,
+}
+
+impl Class {
+    /// Tokens without a class, like identifiers, whitespace and delimiters, are left as is
+    fn of(token: Token) -> Option<Self> {
+        match token {
+            Token::DocComment | Token::Comment => Some(Self::Comment),
+            Token::And
+            | Token::As
+            | Token::Break
+            | Token::Const
+            | Token::Continue
+            | Token::Else
+            | Token::Enum
+            | Token::Fn
+            | Token::For
+            | Token::If
+            | Token::Impl
+            | Token::In
+            | Token::Let
+            | Token::Loop
+            | Token::Match
+            | Token::Mod
+            | Token::Module
+            | Token::Mut
+            | Token::Not
+            | Token::Or
+            | Token::Pub
+            | Token::Return
+            | Token::Struct
+            | Token::Then
+            | Token::Type
+            | Token::Union
+            | Token::Use
+            | Token::While
+            | Token::With => Some(Self::Keyword),
+            Token::Int
+            | Token::Float
+            | Token::Char
+            | Token::String
+            | Token::RawString
+            | Token::MultiLineString => Some(Self::Literal),
+            Token::Ampersand
+            | Token::At
+            | Token::Assign
+            | Token::PlusAssign
+            | Token::MinusAssign
+            | Token::StarAssign
+            | Token::SlashAssign
+            | Token::Dot
+            | Token::DotDot
+            | Token::DotDotEq
+            | Token::Plus
+            | Token::PlusPlus
+            | Token::Pipe
+            | Token::Minus
+            | Token::Star
+            | Token::Slash
+            | Token::Percent
+            | Token::Pound
+            | Token::Colon
+            | Token::ThinArrow
+            | Token::ThickArrow
+            | Token::Eq
+            | Token::NotEq
+            | Token::LessThan
+            | Token::LessThanEq
+            | Token::GreaterThan
+            | Token::GreaterThanEq => Some(Self::Operator),
+            Token::Ident
+            | Token::VSpace
+            | Token::HSpace
+            | Token::BraceOpen
+            | Token::BraceClose
+            | Token::BracketOpen
+            | Token::BracketClose
+            | Token::ParenOpen
+            | Token::ParenClose
+            | Token::Comma
+            | Token::Semi => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Comment => "comment",
+            Self::Keyword => "keyword",
+            Self::Literal => "literal",
+            Self::Operator => "operator",
+        }
+    }
+}
+
+/// Highlights the whole source, invalid tokens are kept unstyled
+pub fn highlight(src: &str, format: Format) -> String {
+    let mut out = String::with_capacity(src.len());
+    if format == Format::Html {
+        out.push_str("<pre class=\"rym\"><code>");
+    }
+
+    for (result, span) in tokenizer(src) {
+        let text = span.src(src);
+        let class = result.ok().and_then(Class::of);
+        match (format, class) {
+            (Format::Ansi, None) => out.push_str(text),
+            (Format::Ansi, Some(class)) => {
+                let _ = match class {
+                    Class::Comment => write!(out, "{}", text.bright_black()),
+                    Class::Keyword => write!(out, "{}", text.magenta()),
+                    Class::Literal => write!(out, "{}", text.green()),
+                    Class::Operator => write!(out, "{}", text.cyan()),
+                };
+            }
+            (Format::Html, None) => escape_html(&mut out, text),
+            (Format::Html, Some(class)) => {
+                let _ = write!(out, "<span class=\"{}\">", class.name());
+                escape_html(&mut out, text);
+                out.push_str("</span>");
+            }
+        }
+    }
+
+    if format == Format::Html {
+        out.push_str("</code></pre>\n");
+    }
+    out
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[test]
+fn highlight_html() {
+    assert_eq!(
+        highlight("const a = 1 < \"b\"; // c", Format::Html),
+        concat!(
+            "<pre class=\"rym\"><code>",
+            "<span class=\"keyword\">const</span> a <span class=\"operator\">=</span> ",
+            "<span class=\"literal\">1</span> <span class=\"operator\">&lt;</span> ",
+            "<span class=\"literal\">&quot;b&quot;</span>; <span class=\"comment\">// c</span>",
+            "</code></pre>\n"
+        )
+    );
+}
@@ -1,18 +1,25 @@
 mod env;
 mod function;
+mod generics;
 
 use std::{
     cmp::PartialOrd,
     collections::HashMap,
-    ops::{Add, Div, Mul, Sub},
+    ops::{Add, Div, Mul, Rem, Sub},
+    rc::Rc,
 };
 
-pub use self::env::Env;
 use self::env::ScopeKind;
+pub use self::env::{Env, ModuleScope};
 pub use self::function::{Call, NativeFunction};
+use self::generics::Bindings;
 use crate::{
-    ast::{BinaryOp, Expr, Function, Literal, Module, Stmt, UnaryOp, VariableKind},
+    ast::{
+        BinaryOp, Expr, Function, Literal, MatchArm, Module, NumberSuffix, Path, Pattern, Stmt,
+        Type, UnaryOp, Use, VariableKind,
+    },
     error::{Diagnostic, Level},
+    span::{Span, Spanned},
 };
 
 #[derive(Debug, Clone)]
@@ -20,11 +27,35 @@ pub enum Value {
     Unit,
     Bool(bool),
     Int(i64),
+    /// An integer with an explicit type like `255u8`, whose overflows are reported
+    SizedInt(i128, NumberSuffix),
     Float(f64),
+    Char(char),
     String(String),
-    Struct(HashMap<String, Value>),
-    Function(Function),
+    Array(Vec<Value>),
+    /// The fields of a tuple, with their names if it is a named tuple
+    Tuple(Vec<(Option<String>, Value)>),
+    Struct(
+        /// Name of the struct type
+        String,
+        HashMap<String, Value>,
+    ),
+    Enum(
+        /// Name of the enum type
+        String,
+        /// Name of the variant
+        String,
+        Option<Box<Value>>,
+    ),
+    /// Shared, because values are cloned whenever a variable is read.
+    /// Functions can access the items of the module they are created in.
+    Function(Rc<Function>, Option<ModuleScope>),
     NativeFunction(NativeFunction),
+    Module(
+        /// Name of the module
+        String,
+        ModuleScope,
+    ),
 }
 
 impl PartialEq for Value {
@@ -32,10 +63,20 @@ impl PartialEq for Value {
         match (self, other) {
             (Self::Bool(l0), Self::Bool(r0)) => l0 == r0,
             (Self::Int(l0), Self::Int(r0)) => l0 == r0,
+            (Self::SizedInt(l0, l1), Self::SizedInt(r0, r1)) => l0 == r0 && l1 == r1,
+            (Self::SizedInt(l0, _), Self::Int(r0)) | (Self::Int(r0), Self::SizedInt(l0, _)) => {
+                *l0 == *r0 as i128
+            }
             (Self::Float(l0), Self::Float(r0)) => l0 == r0,
+            (Self::Char(l0), Self::Char(r0)) => l0 == r0,
             (Self::String(l0), Self::String(r0)) => l0 == r0,
-            (Self::Function(l0), Self::Function(r0)) => l0 == r0,
+            (Self::Array(l0), Self::Array(r0)) => l0 == r0,
+            (Self::Tuple(l0), Self::Tuple(r0)) => l0 == r0,
+            (Self::Struct(l0, l1), Self::Struct(r0, r1)) => l0 == r0 && l1 == r1,
+            (Self::Enum(l0, l1, l2), Self::Enum(r0, r1, r2)) => l0 == r0 && l1 == r1 && l2 == r2,
+            (Self::Function(l0, l1), Self::Function(r0, r1)) => l0 == r0 && l1 == r1,
             (Self::NativeFunction(l0), Self::NativeFunction(r0)) => l0 == r0,
+            (Self::Module(l0, l1), Self::Module(r0, r1)) => l0 == r0 && l1 == r1,
             (Value::Unit, Value::Unit) => true,
             // TODO These cases should not be accessible, protect them via type checking
             _ => false,
@@ -49,11 +90,106 @@ impl std::fmt::Display for Value {
             Self::Unit => write!(f, "()"),
             Self::Bool(inner) => f.write_str(if *inner { "true" } else { "false" }),
             Self::Int(inner) => write!(f, "{inner:#}"),
+            Self::SizedInt(inner, _) => write!(f, "{inner:#}"),
             Self::Float(inner) => write!(f, "{inner:#}"),
+            Self::Char(inner) => write!(f, "{inner}"),
             Self::String(inner) => write!(f, "{inner:#}"),
-            Self::Struct(inner) => write!(f, "<unkown> {inner:#?}"),
-            Self::Function(inner) => write!(f, "{inner:#}"),
+            Self::Array(inner) => {
+                f.write_str("[")?;
+                for (index, item) in inner.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Self::Tuple(inner) => {
+                f.write_str("(")?;
+                for (index, (name, item)) in inner.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if let Some(name) = name {
+                        write!(f, "{name}: ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                // A single value in parentheses is not a tuple
+                f.write_str(if inner.len() == 1 { ",)" } else { ")" })
+            }
+            Self::Struct(name, inner) => write!(f, "{name} {inner:#?}"),
+            Self::Enum(typ, variant, None) => write!(f, "{typ}.{variant}"),
+            Self::Enum(typ, variant, Some(inner)) => write!(f, "{typ}.{variant}({inner})"),
+            Self::Function(inner, _) => write!(f, "{inner:#}"),
             Self::NativeFunction(inner) => write!(f, "{inner:#}"),
+            Self::Module(name, _) => write!(f, "module {name}"),
+        }
+    }
+}
+
+impl Value {
+    /// Name of the type, for values of user defined types
+    pub fn type_name(&self) -> Option<&str> {
+        match self {
+            Value::Struct(name, _) | Value::Enum(name, ..) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The field of a struct or tuple, the fields of tuples can also be accessed by their index.
+    /// The fields of modules are their items.
+    pub fn field(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(_, fields) => fields.get(key),
+            Value::Module(_, module) => module.get(key),
+            Value::Tuple(fields) => {
+                Self::tuple_field_index(fields, key).map(|index| &fields[index].1)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn field_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Value::Struct(_, fields) => fields.get_mut(key),
+            Value::Tuple(fields) => {
+                Self::tuple_field_index(fields, key).map(|index| &mut fields[index].1)
+            }
+            _ => None,
+        }
+    }
+
+    fn tuple_field_index(fields: &[(Option<String>, Value)], key: &str) -> Option<usize> {
+        match key.parse::<usize>() {
+            Ok(index) => (index < fields.len()).then_some(index),
+            Err(_) => fields
+                .iter()
+                .position(|(name, _)| name.as_deref() == Some(key)),
+        }
+    }
+
+    pub fn concat(&self, rhs: &Self) -> Option<Self> {
+        Some(match (self, rhs) {
+            (Value::Array(lhs), Value::Array(rhs)) => {
+                Value::Array(Vec::from_iter(lhs.iter().chain(rhs.iter()).cloned()))
+            }
+            (Value::String(lhs), Value::String(rhs)) => Value::String(lhs.to_owned() + rhs),
+            _ => return None,
+        })
+    }
+}
+
+impl From<Literal> for Value {
+    fn from(lit: Literal) -> Self {
+        match lit {
+            Literal::Bool(inner) => Value::Bool(inner),
+            // Already checked to be in range while parsing
+            Literal::Int(inner, None) => Value::Int(inner as i64),
+            Literal::Int(inner, Some(suffix)) => Value::SizedInt(inner.into(), suffix),
+            Literal::Float(inner, _) => Value::Float(inner),
+            Literal::Char(inner) => Value::Char(inner),
+            Literal::String(inner) => Value::String(inner),
         }
     }
 }
@@ -62,7 +198,10 @@ pub enum ControlFlow {
     /// Crashes the entire evaluation context
     Exit,
     None(Value),
-    Break(Value),
+    /// Unwinds to the loop or block with the label, or to the innermost loop
+    Break(Option<String>, Value),
+    /// Unwinds to the loop with the label, or to the innermost loop
+    Continue(Option<String>),
     Return(Value),
 }
 
@@ -81,166 +220,268 @@ pub trait Interpret {
 
 impl Interpret for Module {
     fn eval(self, env: &mut Env) -> ControlFlow {
-        // TODO sort based on dependency
-        // self
-        // 	.constants
-        // 	.sort_by(|Constant { expr: l, .. }, Constant { expr: r, .. }| match (l, r) {});
+        // Separate from the prelude, so that sub modules only see the prelude
+        env.push_scope(ScopeKind::Module);
+        let result = eval_items(env, self);
+
+        // TODO only do this when requested, ie. in main.rym file
+        let main = match (&result, env.get("main")) {
+            (ControlFlow::None(_), Some(main)) => Some(main.clone()),
+            _ => None,
+        };
+        match main {
+            Some(Value::Function(val, module)) => {
+                call_function(env, &val, module.as_ref(), vec![], vec![], val.body.span);
+            }
+            Some(_) => todo!(),
+            None => {}
+        }
 
-        for (name, _, expr) in self.constants {
-            // Top level, ignoring control flow
+        env.pop_scope();
+        result
+    }
+}
+
+/// Evaluates the items of a module into the current scope
+fn eval_items(env: &mut Env, module: Module) -> ControlFlow {
+    // TODO sort based on dependency
+    // self
+    // 	.constants
+    // 	.sort_by(|Constant { expr: l, .. }, Constant { expr: r, .. }| match (l, r) {});
+
+    for (name, typ, _, visibility) in module.types {
+        env.declare(&name, visibility);
+        env.create_type(name.val, typ);
+    }
+
+    for (name, _, expr, _, visibility) in module.constants {
+        // Top level, ignoring control flow
+        let val = match expr.eval(env) {
+            ControlFlow::None(inner)
+            | ControlFlow::Break(_, inner)
+            | ControlFlow::Return(inner) => inner,
+            ControlFlow::Continue(_) => Value::Unit,
+            exit => return exit,
+        };
+        env.declare(&name, visibility);
+        env.create(name.val, VariableKind::Const, val);
+    }
+
+    for item in module.impls {
+        // Already reported while parsing
+        let Some(typ) = item.type_name() else {
+            continue;
+        };
+        env.push_self_type(typ.clone());
+        for (name, _, mut expr, _, visibility) in item.constants {
+            // Associated functions share the generic parameters of the impl block
+            if let Expr::Function(func) = &mut expr.val {
+                func.generics.splice(0..0, item.generics.iter().cloned());
+            }
             let val = match expr.eval(env) {
                 ControlFlow::None(inner)
-                | ControlFlow::Break(inner)
+                | ControlFlow::Break(_, inner)
                 | ControlFlow::Return(inner) => inner,
+                ControlFlow::Continue(_) => Value::Unit,
                 exit => return exit,
             };
-            env.create(name, VariableKind::Const, val);
+            env.create_associated(typ.clone(), name, visibility, val);
+        }
+        env.pop_self_type();
+    }
+
+    for sub_module in module.sub_modules {
+        let (name, visibility) = (sub_module.name.clone(), sub_module.visibility);
+        let val = default_flow!(eval_sub_module(env, sub_module));
+        env.declare(&name, visibility);
+        env.create(name.val, VariableKind::Const, val);
+    }
+
+    for Use { path, items } in module.uses {
+        if let Err(diagnostic) = env.use_items(&path, &items) {
+            diagnostic.emit(env.emitter.clone());
+            return ControlFlow::Exit;
         }
+    }
 
-        // TODO only do this when requested, ie. in main.rym file
-        if let Some(main) = env.get("main") {
-            match main {
-                Value::Function(val) => {
-                    // TODO avoid cloning here
-                    val.clone().call(env, vec![]);
-                }
-                _ => todo!(),
+    ControlFlow::None(Value::Unit)
+}
+
+/// Evaluates a module in its own scope, the module value gives access to its items afterwards
+fn eval_sub_module(env: &mut Env, module: Module) -> ControlFlow {
+    let name = module.name.val.clone();
+    let scope = env.push_module();
+    let result = eval_items(env, module);
+    env.pop_module();
+    default_flow!(result);
+    ControlFlow::None(Value::Module(name, scope))
+}
+
+/// Evaluates the items of `module` in a new scope, followed by the body of a test
+/// inside of the sub module at `path`
+pub fn eval_test(
+    env: &mut Env,
+    module: Module,
+    path: &[String],
+    body: Spanned<Expr>,
+) -> ControlFlow {
+    env.push_scope(ScopeKind::Module);
+    let result = match eval_items(env, module) {
+        ControlFlow::None(_) => {
+            let mut scope: Option<ModuleScope> = None;
+            for name in path {
+                let value = match &scope {
+                    None => env.get(name),
+                    Some(scope) => scope.get(name),
+                };
+                let Some(Value::Module(_, module)) = value else {
+                    panic!("Internal Error: Test is in unknown module `{name}`");
+                };
+                scope = Some(module.clone());
             }
+            call_in_module(env, scope.as_ref(), |env| body.eval(env))
         }
+        exit => exit,
+    };
+    env.pop_scope();
+    result
+}
+
+/// Calls a function inside of the module it was created in
+fn call_function(
+    env: &mut Env,
+    func: &Function,
+    module: Option<&ModuleScope>,
+    args: Vec<Value>,
+    named_args: Vec<(Spanned<String>, Value)>,
+    span: Span,
+) -> ControlFlow {
+    call_in_module(env, module, |env| func.call(env, args, named_args, span))
+}
 
-        ControlFlow::Break(Value::Unit)
+fn call_in_module(
+    env: &mut Env,
+    module: Option<&ModuleScope>,
+    f: impl FnOnce(&mut Env) -> ControlFlow,
+) -> ControlFlow {
+    let entered = module.is_some_and(|module| env.enter_module(module));
+    let result = f(env);
+    if entered {
+        env.leave_module();
     }
+    result
 }
 
-impl Interpret for Stmt {
+impl Interpret for Spanned<Stmt> {
     fn eval(self, env: &mut Env) -> ControlFlow {
-        match self {
+        match self.val {
             Stmt::Expr(expr) => expr.eval(env),
-            Stmt::Variable(kind, name, typ, expr) => {
+            Stmt::Variable(kind, name, typ, expr, _) => {
                 let val = default_flow!(expr.eval(env));
-                env.create(name, kind, val);
+                env.create(name.val, kind, val);
+                ControlFlow::None(Value::Unit)
+            }
+            Stmt::Type(name, typ, _) => {
+                env.create_type(name.val, typ);
                 ControlFlow::None(Value::Unit)
             }
+            // Already reported while parsing
+            Stmt::Error => ControlFlow::Exit,
             _ => todo!(),
         }
     }
 }
 
-impl Interpret for Expr {
+impl Interpret for Spanned<Expr> {
     fn eval(self, env: &mut Env) -> ControlFlow {
-        let result = match self {
+        let Spanned { val, span } = self;
+        let result = match val {
             Expr::Unit => Value::Unit,
-            Expr::Literal(lit) => match lit {
-                Literal::Bool(inner) => Value::Bool(inner),
-                Literal::Int(inner) => Value::Int(inner),
-                Literal::Float(inner) => Value::Float(inner),
-                Literal::String(inner) => Value::String(inner),
-            },
-            Expr::Array(array) => {
-                todo!();
+            Expr::Literal(lit) => Value::from(lit),
+            Expr::Array(items) => {
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    values.push(default_flow!(item.eval(env)));
+                }
+                Value::Array(values)
             }
             Expr::ArrayWithRepeat(value, length) => {
-                todo!();
+                default_flow!(eval_array_with_repeat(env, *value, *length))
             }
-            Expr::Struct(name, fields) => Value::Struct({
-                let mut map = HashMap::with_capacity(fields.len());
+            Expr::Tuple(fields) => {
+                let mut values = Vec::with_capacity(fields.len());
                 for (name, expr) in fields {
-                    map.insert(name, default_flow!(expr.eval(env)));
-                }
-                map
-            }),
-            Expr::Function(func) => Value::Function(func),
-
-            Expr::Unary(op, expr) => match (op, default_flow!(expr.eval(env))) {
-                (UnaryOp::Neg, Value::Float(val)) => Value::Float(-val),
-                (UnaryOp::Neg, Value::Int(val)) => Value::Int(-val),
-                (UnaryOp::Not, Value::Bool(val)) => Value::Bool(!val),
-
-                (_op, _val) => todo!(),
-            },
-            Expr::Binary(op, lhs, rhs) => match (
-                op,
-                default_flow!(lhs.eval(env)),
-                default_flow!(rhs.eval(env)),
-            ) {
-                (op, Value::Float(lhs), Value::Float(rhs)) => {
-                    eval_binary(op, lhs, rhs, Value::Float)
-                }
-                (op, Value::Float(lhs), Value::Int(rhs)) => {
-                    let rhs = rhs as f64;
-                    eval_binary(op, lhs, rhs, Value::Float)
+                    values.push((name.map(|name| name.val), default_flow!(expr.eval(env))));
                 }
-                (op, Value::Int(lhs), Value::Float(rhs)) => {
-                    let lhs = lhs as f64;
-                    eval_binary(op, lhs, rhs, Value::Float)
-                }
-
-                (op, Value::Int(lhs), Value::Int(rhs)) => eval_binary(op, lhs, rhs, Value::Int),
-
-                (BinaryOp::Add, Value::String(lhs), Value::String(rhs)) => {
-                    Value::String(lhs + &rhs)
-                }
-
-                (BinaryOp::Eq, lhs, rhs) => Value::Bool(lhs == rhs),
-                (BinaryOp::NotEq, lhs, rhs) => Value::Bool(lhs != rhs),
+                Value::Tuple(values)
+            }
+            Expr::Struct(path, generic_args, fields) => {
+                default_flow!(eval_struct(env, path, generic_args, fields, span))
+            }
+            Expr::Function(func) => Value::Function(Rc::new(*func), env.current_module()),
+            Expr::Module(module) => default_flow!(eval_sub_module(env, *module)),
 
-                (_op, _lhs, _rhs) => todo!(),
-            },
-            Expr::Call(lhs, args) => {
-                let result = match default_flow!(lhs.eval(env)) {
-                    Value::Function(inner) => {
-                        let mut arg_values = vec![];
-                        for expr in args {
-                            arg_values.push(default_flow!(expr.eval(env)));
-                        }
-                        inner.call(env, arg_values)
-                    }
-                    Value::NativeFunction(inner) => {
-                        let mut arg_values = vec![];
-                        for expr in args {
-                            arg_values.push(default_flow!(expr.eval(env)));
-                        }
-                        inner.call(env, arg_values)
-                    }
-                    _ => todo!("Add error, value is not a function."),
+            Expr::Unary(op, expr) => default_flow!(eval_unary(env, op, *expr, span)),
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), lhs, rhs) => {
+                default_flow!(eval_logical(env, op, *lhs, *rhs))
+            }
+            Expr::Binary(BinaryOp::Concat, lhs, rhs) => {
+                let lhs = default_flow!(lhs.eval(env));
+                let rhs = default_flow!(rhs.eval(env));
+                let Some(val) = lhs.concat(&rhs) else {
+                    Diagnostic::spanned(
+                        span,
+                        Level::Error,
+                        format!("Unable to concatenate '{lhs}' and '{rhs}'"),
+                    )
+                    .with_child(
+                        vec![],
+                        Level::Note,
+                        "Only two strings or two arrays can be concatenated",
+                    )
+                    .emit(env.emitter.clone());
+                    return ControlFlow::Exit;
                 };
-                match result {
-                    ControlFlow::Exit => return ControlFlow::Exit,
-                    ControlFlow::None(inner) => inner,
-                    ControlFlow::Break(inner) => inner,
-                    ControlFlow::Return(inner) => inner,
+                val
+            }
+            Expr::Binary(
+                op @ (BinaryOp::Assign
+                | BinaryOp::AddAssign
+                | BinaryOp::SubAssign
+                | BinaryOp::MulAssign
+                | BinaryOp::DivAssign),
+                lhs,
+                rhs,
+            ) => default_flow!(eval_assign(env, op, *lhs, *rhs)),
+            Expr::Binary(op, lhs, rhs) => {
+                let rhs_span = rhs.span;
+                let lhs = default_flow!(lhs.eval(env));
+                let rhs = default_flow!(rhs.eval(env));
+                match eval_binary_values(op, lhs, rhs, span, rhs_span) {
+                    Ok(val) => val,
+                    Err(diagnostic) => {
+                        diagnostic.emit(env.emitter.clone());
+                        return ControlFlow::Exit;
+                    }
                 }
             }
+            Expr::Call(lhs, args, named_args) => {
+                default_flow!(eval_call(env, *lhs, args, named_args, span))
+            }
 
             Expr::Ident(name) => match env.get(&name) {
                 // TODO Only clone when needed / faster
                 Some(val) => val.clone(),
                 None => {
-                    Diagnostic::new(Level::Error, format!("Unable to find '{name}'"))
+                    Diagnostic::spanned(span, Level::Error, format!("Unable to find '{name}'"))
                         .emit(env.emitter.clone());
                     return ControlFlow::Exit;
                 }
             },
-            Expr::FieldAccess(lhs, key) => {
-                let val = default_flow!(lhs.eval(env));
-                let Some(field) = (match &val {
-                    Value::Struct(fields) => fields.get(&key).cloned(),
-                    _ => None,
-                }) else {
-                    Diagnostic::new(
-                        Level::Error,
-                        format!("Field '{key}' does not exist on value '{val}'"),
-                    )
-                    .emit(env.emitter.clone());
-                    return ControlFlow::Exit;
-                };
-                field
-            }
-            Expr::Subscript(_lhs, _rhs) => {
-                // TODO
-                debug_todo();
-                Value::Unit
-            }
+            Expr::FieldAccess(lhs, key) => default_flow!(eval_field_access(env, *lhs, key, span)),
+            Expr::Subscript(lhs, index) => default_flow!(eval_subscript(env, *lhs, *index)),
+            // Generic arguments are only checked when calling a function or using a type
+            Expr::Generic(inner, _) => default_flow!(inner.eval(env)),
 
             Expr::IfElse(cond_expr, then_expr, else_expr) => {
                 let Value::Bool(condition) = default_flow!(cond_expr.eval(env)) else {
@@ -252,40 +493,1390 @@ impl Interpret for Expr {
                     default_flow!(else_expr.eval(env))
                 }
             }
-            Expr::Block(stmts) => {
+            Expr::Block(stmts, final_expr) => {
                 env.push_scope(ScopeKind::Expr);
-                let mut result = Value::Unit;
-                'stmts_loop: for stmt in stmts {
-                    match stmt.eval(env) {
-                        ControlFlow::None(_) => (),
-                        ControlFlow::Break(inner) => {
-                            result = inner;
-                            break 'stmts_loop;
-                        }
-                        control_flow => return control_flow,
-                    }
-                }
+                let result = eval_block(env, stmts, final_expr);
                 env.pop_scope();
-                result
+                default_flow!(result)
+            }
+            Expr::Match(scrutinee, arms) => default_flow!(eval_match(env, *scrutinee, arms)),
+            Expr::Loop(body) => default_flow!(eval_loop(env, None, None, *body)),
+            Expr::While(cond, body) => default_flow!(eval_loop(env, None, Some(*cond), *body)),
+            Expr::For(pattern, iter, body) => {
+                default_flow!(eval_for(env, None, pattern, *iter, *body))
             }
-            Expr::Break(expr) => return ControlFlow::Break(default_flow!(expr.eval(env))),
+            Expr::Label(label, inner) => default_flow!(eval_labelled(env, label.val, *inner)),
+            Expr::Break(label, expr) => {
+                let val = default_flow!(expr.eval(env));
+                return ControlFlow::Break(label.map(|label| label.val), val);
+            }
+            Expr::Continue(label) => return ControlFlow::Continue(label.map(|label| label.val)),
             Expr::Return(expr) => return ControlFlow::Return(default_flow!(expr.eval(env))),
+
+            // Already reported while parsing
+            Expr::Error => return ControlFlow::Exit,
         };
 
         ControlFlow::None(result)
     }
 }
 
+/// Evaluates a struct literal like `Point { x = 1, y = 2 }`
+fn eval_struct(
+    env: &mut Env,
+    path: Path,
+    generic_args: Vec<Spanned<Type>>,
+    fields: Vec<(Spanned<String>, Spanned<Expr>)>,
+    span: Span,
+) -> ControlFlow {
+    let Ok(defaults) = check_struct_fields(env, &path, &fields, span) else {
+        return ControlFlow::Exit;
+    };
+    let mut values = Vec::with_capacity(fields.len());
+    for (name, expr) in fields {
+        let span = expr.span;
+        values.push((name.val, Spanned::new(default_flow!(expr.eval(env)), span)));
+    }
+    if let Err(diagnostic) = check_struct_generics(env, &path, &generic_args, &values, span) {
+        diagnostic.emit(env.emitter.clone());
+        return ControlFlow::Exit;
+    }
+    let mut map = HashMap::with_capacity(values.len() + defaults.len());
+    map.extend(values.into_iter().map(|(name, value)| (name, value.val)));
+    map.extend(defaults);
+    // Values of types from other modules have the same name as in their module
+    let name = path.parts.last().map_or("", |name| env.resolve_self(name));
+    ControlFlow::None(Value::Struct(name.to_string(), map))
+}
+
+/// Evaluates `lhs.key`, which is an associated item, an enum variant or a field
+fn eval_field_access(env: &mut Env, lhs: Spanned<Expr>, key: String, span: Span) -> ControlFlow {
+    match find_associated(env, &lhs, &key, span) {
+        Ok(Some(Associated { value, .. })) => return ControlFlow::None(value),
+        Ok(None) => {}
+        Err(()) => return ControlFlow::Exit,
+    }
+    let Ok(found) = find_variant(env, &lhs, &key, span) else {
+        return ControlFlow::Exit;
+    };
+    if let Some(Variant { typ, inner, .. }) = found {
+        if inner.is_some() {
+            Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!("Variant `{typ}.{key}` needs a value"),
+            )
+            .with_child(
+                vec![],
+                Level::Help,
+                format!("Create it with `{typ}.{key}(value)`"),
+            )
+            .emit(env.emitter.clone());
+            return ControlFlow::Exit;
+        }
+        return ControlFlow::None(Value::Enum(typ, key, None));
+    }
+
+    let val = default_flow!(lhs.eval(env));
+    if let Value::Module(_, module) = &val {
+        if let Err(diagnostic) = env.check_access(module, &key, span) {
+            diagnostic.emit(env.emitter.clone());
+            return ControlFlow::Exit;
+        }
+    }
+    let Some(field) = val.field(&key).cloned() else {
+        Diagnostic::spanned(
+            span,
+            Level::Error,
+            format!("Field '{key}' does not exist on value '{val}'"),
+        )
+        .emit(env.emitter.clone());
+        return ControlFlow::Exit;
+    };
+    ControlFlow::None(field)
+}
+
+fn eval_unary(env: &mut Env, op: UnaryOp, expr: Spanned<Expr>, span: Span) -> ControlFlow {
+    let result = match (op, expr.val) {
+        // Evaluated directly, because `-9223372036854775808` only fits into an int after negating it
+        (UnaryOp::Neg, Expr::Literal(Literal::Int(int, suffix))) => {
+            int_value(Some(-i128::from(int)), suffix, span)
+        }
+        (op, val) => match (op, default_flow!(Spanned::new(val, expr.span).eval(env))) {
+            (UnaryOp::Neg, Value::Float(val)) => Ok(Value::Float(-val)),
+            (UnaryOp::Neg, Value::Int(val)) => int_value(Some(-i128::from(val)), None, span),
+            (UnaryOp::Neg, Value::SizedInt(val, typ)) => int_value(Some(-val), Some(typ), span),
+            (UnaryOp::Not, Value::Bool(val)) => Ok(Value::Bool(!val)),
+
+            (op, val) => Err(Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!(
+                    "Operator `{}` is not supported for `{}`",
+                    op.symbol(),
+                    generics::type_of(&val, span)
+                ),
+            )),
+        },
+    };
+    match result {
+        Ok(value) => ControlFlow::None(value),
+        Err(diagnostic) => {
+            diagnostic.emit(env.emitter.clone());
+            ControlFlow::Exit
+        }
+    }
+}
+
+/// Evaluates the operator on both values, `rhs_span` is where division by zero is reported
+fn eval_binary_values(
+    op: BinaryOp,
+    lhs: Value,
+    rhs: Value,
+    span: Span,
+    rhs_span: Span,
+) -> Result<Value, Diagnostic> {
+    Ok(match (op, lhs, rhs) {
+        (op, Value::Float(lhs), Value::Float(rhs)) => eval_binary(op, lhs, rhs, Value::Float),
+        (op, Value::Float(lhs), Value::Int(rhs)) => {
+            let rhs = rhs as f64;
+            eval_binary(op, lhs, rhs, Value::Float)
+        }
+        (op, Value::Int(lhs), Value::Float(rhs)) => {
+            let lhs = lhs as f64;
+            eval_binary(op, lhs, rhs, Value::Float)
+        }
+
+        (op, Value::SizedInt(lhs, _), Value::Float(rhs)) => {
+            eval_binary(op, lhs as f64, rhs, Value::Float)
+        }
+        (op, Value::Float(lhs), Value::SizedInt(rhs, _)) => {
+            eval_binary(op, lhs, rhs as f64, Value::Float)
+        }
+
+        (op, Value::Int(lhs), Value::Int(rhs)) => {
+            eval_int(op, lhs.into(), rhs.into(), None, span, rhs_span)?
+        }
+        // Integers without a suffix take the type of the other side
+        (op, Value::SizedInt(lhs, typ), Value::Int(rhs)) => {
+            eval_int(op, lhs, rhs.into(), Some(typ), span, rhs_span)?
+        }
+        (op, Value::Int(lhs), Value::SizedInt(rhs, typ)) => {
+            eval_int(op, lhs.into(), rhs, Some(typ), span, rhs_span)?
+        }
+        (op, Value::SizedInt(lhs, lhs_typ), Value::SizedInt(rhs, rhs_typ)) => {
+            if lhs_typ != rhs_typ {
+                return Err(Diagnostic::spanned(
+                    span,
+                    Level::Error,
+                    format!(
+                        "Operator `{}` is not supported for `{lhs_typ}` and `{rhs_typ}`",
+                        op.symbol()
+                    ),
+                ));
+            }
+            eval_int(op, lhs, rhs, Some(lhs_typ), span, rhs_span)?
+        }
+        (
+            op @ (BinaryOp::LessThan
+            | BinaryOp::LessThanEq
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanEq
+            | BinaryOp::Eq
+            | BinaryOp::NotEq),
+            Value::Char(lhs),
+            Value::Char(rhs),
+        ) => eval_comparison(op, lhs, rhs),
+        (op, Value::Char(_), Value::Char(_)) => {
+            return Err(Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!("Operator `{}` is not supported for `char`", op.symbol()),
+            ));
+        }
+
+        (BinaryOp::Add, Value::String(lhs), Value::String(rhs)) => Value::String(lhs + &rhs),
+
+        (BinaryOp::Eq, lhs, rhs) => Value::Bool(lhs == rhs),
+        (BinaryOp::NotEq, lhs, rhs) => Value::Bool(lhs != rhs),
+
+        (op, lhs, rhs) => {
+            return Err(Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!(
+                    "Operator `{}` is not supported for `{}` and `{}`",
+                    op.symbol(),
+                    generics::type_of(&lhs, span),
+                    generics::type_of(&rhs, span)
+                ),
+            ));
+        }
+    })
+}
+
+/// Evaluates arithmetic and comparisons of integers of the type, or of ints if it is `None`
+fn eval_int(
+    op: BinaryOp,
+    lhs: i128,
+    rhs: i128,
+    typ: Option<NumberSuffix>,
+    span: Span,
+    rhs_span: Span,
+) -> Result<Value, Diagnostic> {
+    let result = match op {
+        BinaryOp::Add => lhs.checked_add(rhs),
+        BinaryOp::Sub => lhs.checked_sub(rhs),
+        BinaryOp::Mul => lhs.checked_mul(rhs),
+        BinaryOp::Div | BinaryOp::Mod if rhs == 0 => {
+            let error = Diagnostic::spanned(rhs_span, Level::Error, "Division by zero");
+            return Err(error.with_child(rhs_span, Level::Error, "The divisor is zero"));
+        }
+        BinaryOp::Div => lhs.checked_div(rhs),
+        BinaryOp::Mod => lhs.checked_rem(rhs),
+        _ => return Ok(eval_comparison(op, lhs, rhs)),
+    };
+    int_value(result, typ, span)
+}
+
+/// An integer of the type, or an int if it is `None`.
+/// Reports an overflow if the result is missing or does not fit into the type.
+fn int_value(
+    int: Option<i128>,
+    typ: Option<NumberSuffix>,
+    span: Span,
+) -> Result<Value, Diagnostic> {
+    let (min, max) = NumberSuffix::int_range(typ);
+    match (int, typ) {
+        (Some(int), None) if (min..=max).contains(&int) => Ok(Value::Int(int as i64)),
+        (Some(int), Some(typ)) if (min..=max).contains(&int) => Ok(Value::SizedInt(int, typ)),
+        _ => {
+            let (label, name) = match typ {
+                Some(typ) => (format!("Does not fit into `{typ}`"), format!("`{typ}`")),
+                None => ("Does not fit into an int".to_string(), "an int".to_string()),
+            };
+            Err(Diagnostic::spanned(span, Level::Error, "Integer overflow")
+                .with_child(span, Level::Error, label)
+                .with_child(
+                    vec![],
+                    Level::Note,
+                    format!("The range of {name} is {min}..={max}"),
+                ))
+        }
+    }
+}
+
+/// A step from a variable to the part of it that is assigned to
+enum Accessor {
+    Field(String, Span),
+    Index(Value, Span),
+}
+
+/// Evaluates `=` and compound assignments like `+=` to variables, fields and array elements
+fn eval_assign(
+    env: &mut Env,
+    op: BinaryOp,
+    target: Spanned<Expr>,
+    value: Spanned<Expr>,
+) -> ControlFlow {
+    let (target_span, value_span, span) = (target.span, value.span, target.span.to(value.span));
+    let value = default_flow!(value.eval(env));
+
+    // Walk from the assigned place outwards to the variable it is part of
+    let mut accessors = vec![];
+    let mut place = target;
+    let name = loop {
+        match place.val {
+            Expr::Ident(name) => break name,
+            Expr::FieldAccess(lhs, field) => {
+                accessors.push(Accessor::Field(field, place.span));
+                place = *lhs;
+            }
+            Expr::Subscript(lhs, index) => {
+                let index_span = index.span;
+                accessors.push(Accessor::Index(default_flow!(index.eval(env)), index_span));
+                place = *lhs;
+            }
+            _ => {
+                Diagnostic::spanned(
+                    place.span,
+                    Level::Error,
+                    "Unable to assign to this expression",
+                )
+                .with_child(
+                    vec![],
+                    Level::Note,
+                    "Only variables, fields and array elements can be assigned to",
+                )
+                .emit(env.emitter.clone());
+                return ControlFlow::Exit;
+            }
+        }
+    };
+
+    let emitter = env.emitter.clone();
+    let Some(kind) = env.get_kind(&name) else {
+        Diagnostic::spanned(place.span, Level::Error, format!("Unable to find '{name}'"))
+            .emit(emitter);
+        return ControlFlow::Exit;
+    };
+    if kind != VariableKind::LetMut {
+        let what = match kind {
+            VariableKind::Const => "the constant",
+            _ => "the immutable variable",
+        };
+        Diagnostic::spanned(
+            target_span,
+            Level::Error,
+            format!("Unable to assign to {what} `{name}`"),
+        )
+        .with_child(
+            vec![],
+            Level::Help,
+            format!("Declare it with `let mut {name}` to allow assignments"),
+        )
+        .emit(emitter);
+        return ControlFlow::Exit;
+    }
+
+    let mut slot = env
+        .get_mut(&name)
+        .expect("Internal Error: Mutable variables are never part of evaluated modules");
+    for accessor in accessors.into_iter().rev() {
+        // Checked before borrowing mutably, so the value can be part of the error
+        let error = match (&accessor, &*slot) {
+            (Accessor::Field(field, _), val) if val.field(field).is_some() => None,
+            (Accessor::Field(field, span), other) => Some(Diagnostic::spanned(
+                *span,
+                Level::Error,
+                format!("Field '{field}' does not exist on value '{other}'"),
+            )),
+            (Accessor::Index(index, span), Value::Array(items)) => array_index(index, items.len())
+                .is_none()
+                .then(|| index_error(*span, index, items.len())),
+            (Accessor::Index(_, span), other) => Some(Diagnostic::spanned(
+                *span,
+                Level::Error,
+                format!("Unable to index into '{other}'"),
+            )),
+        };
+        if let Some(error) = error {
+            error.emit(emitter);
+            return ControlFlow::Exit;
+        }
+
+        slot = match (accessor, slot) {
+            (Accessor::Field(field, _), val) => val.field_mut(&field).unwrap(),
+            (Accessor::Index(index, _), Value::Array(items)) => {
+                let index = array_index(&index, items.len()).unwrap();
+                &mut items[index]
+            }
+            _ => unreachable!("Checked above"),
+        };
+    }
+
+    *slot = match op.compound_op() {
+        Some(op) => match eval_binary_values(op, slot.clone(), value, span, value_span) {
+            Ok(val) => val,
+            Err(diagnostic) => {
+                diagnostic.emit(emitter);
+                return ControlFlow::Exit;
+            }
+        },
+        None => value,
+    };
+    ControlFlow::None(Value::Unit)
+}
+
+/// Evaluates `lhs(args, named: value)`, where `lhs` is a function, a method or an enum variant
+fn eval_call(
+    env: &mut Env,
+    lhs: Spanned<Expr>,
+    args: Vec<Spanned<Expr>>,
+    named_args: Vec<(Spanned<String>, Spanned<Expr>)>,
+    span: Span,
+) -> ControlFlow {
+    // `func[A](..)` gives the generic arguments of the function explicitly
+    let (lhs, generic_args) = match lhs.val {
+        Expr::Generic(inner, generic_args) => (*inner, generic_args),
+        _ => (lhs, vec![]),
+    };
+
+    if let Expr::FieldAccess(typ, variant) = &lhs.val {
+        let Ok(found) = find_variant(env, typ, variant, lhs.span) else {
+            return ControlFlow::Exit;
+        };
+        if let Some(Variant {
+            typ,
+            inner,
+            mut generics,
+        }) = found
+        {
+            let (Some(inner), 1, true) = (inner, args.len(), named_args.is_empty()) else {
+                Diagnostic::spanned(
+                    span,
+                    Level::Error,
+                    format!("Variant `{typ}.{variant}` takes exactly one value"),
+                )
+                .emit(env.emitter.clone());
+                return ControlFlow::Exit;
+            };
+            if !generic_args.is_empty() {
+                generics::arity_error(&format!("`{typ}.{variant}`"), 0, &generic_args)
+                    .emit(env.emitter.clone());
+                return ControlFlow::Exit;
+            }
+            let variant = variant.clone();
+            let arg = args.into_iter().next().unwrap();
+            let arg_span = arg.span;
+            let value = default_flow!(arg.eval(env));
+            if generics.is_generic() {
+                if let Err(diagnostic) = generics.infer(env, &inner.val, &value, arg_span) {
+                    diagnostic.emit(env.emitter.clone());
+                    return ControlFlow::Exit;
+                }
+            }
+            return ControlFlow::None(Value::Enum(typ, variant, Some(Box::new(value))));
+        }
+    }
+
+    let mut arg_values = vec![];
+    let mut self_type = None;
+    // Generic arguments of the type of an associated function, like in `List[int].new()`
+    let mut type_args = vec![];
+    // Name of the field, when calling a field of the receiver like `point.x(..)`
+    let mut field = None;
+    let lhs_span = lhs.span;
+    let callee = match lhs.val {
+        Expr::FieldAccess(receiver, key) => {
+            let Ok(associated) = find_associated(env, &receiver, &key, lhs.span) else {
+                return ControlFlow::Exit;
+            };
+            if let Some(associated) = associated {
+                self_type = Some(associated.typ);
+                type_args = associated.generic_args;
+                associated.value
+            } else {
+                let receiver_span = receiver.span;
+                let receiver = default_flow!(receiver.eval(env));
+                let Ok(method) = find_method(env, &receiver, &key, lhs.span) else {
+                    return ControlFlow::Exit;
+                };
+                if method.pass_receiver {
+                    arg_values.push(Spanned::new(receiver, receiver_span));
+                } else if !matches!(receiver, Value::Module(..)) {
+                    field = Some(key);
+                }
+                self_type = method.self_type;
+                method.callee
+            }
+        }
+        _ => default_flow!(lhs.eval(env)),
+    };
+    for expr in args {
+        let span = expr.span;
+        arg_values.push(Spanned::new(default_flow!(expr.eval(env)), span));
+    }
+    let mut named_values = vec![];
+    for (name, expr) in named_args {
+        named_values.push((name, default_flow!(expr.eval(env))));
+    }
+    if let Some(typ) = &self_type {
+        env.push_self_type(typ.clone());
+    }
+    let result = match callee {
+        Value::Function(inner, module) if inner.generics.is_empty() && generic_args.is_empty() => {
+            let arg_values = arg_values.into_iter().map(|arg| arg.val).collect();
+            call_function(env, &inner, module.as_ref(), arg_values, named_values, span)
+        }
+        Value::Function(inner, module) => {
+            match Bindings::for_function(env, &inner, &type_args, &generic_args) {
+                Ok(bindings) => call_generic(
+                    env,
+                    &inner,
+                    module.as_ref(),
+                    bindings,
+                    arg_values,
+                    named_values,
+                    span,
+                ),
+                Err(diagnostic) => {
+                    diagnostic.emit(env.emitter.clone());
+                    ControlFlow::Exit
+                }
+            }
+        }
+        Value::NativeFunction(_) if !generic_args.is_empty() => {
+            generics::arity_error("This function", 0, &generic_args).emit(env.emitter.clone());
+            ControlFlow::Exit
+        }
+        Value::NativeFunction(inner) => {
+            let arg_values = arg_values.into_iter().map(|arg| arg.val).collect();
+            inner.call(env, arg_values, named_values, span)
+        }
+        callee => {
+            let typ = generics::type_of(&callee, lhs_span);
+            let message = match field {
+                Some(field) => format!("`{field}` is a field of type `{typ}`, not a function"),
+                None => format!("`{typ}` is not a function"),
+            };
+            Diagnostic::spanned(lhs_span, Level::Error, message).emit(env.emitter.clone());
+            ControlFlow::Exit
+        }
+    };
+    if self_type.is_some() {
+        env.pop_self_type();
+    }
+    match result {
+        ControlFlow::Exit => ControlFlow::Exit,
+        ControlFlow::None(inner) | ControlFlow::Break(_, inner) | ControlFlow::Return(inner) => {
+            ControlFlow::None(inner)
+        }
+        ControlFlow::Continue(_) => ControlFlow::None(Value::Unit),
+    }
+}
+
+/// Calls a generic function, whose generic parameters are bound by the arguments it is called with.
+/// The arguments and the return value are checked against the types of the parameters.
+fn call_generic(
+    env: &mut Env,
+    func: &Function,
+    module: Option<&ModuleScope>,
+    mut bindings: Bindings,
+    args: Vec<Spanned<Value>>,
+    named_args: Vec<(Spanned<String>, Value)>,
+    span: Span,
+) -> ControlFlow {
+    // Types can only be inferred from arguments that have a parameter
+    if let Err(diagnostic) = function::check_args(func, args.len(), &named_args, span) {
+        diagnostic.emit(env.emitter.clone());
+        return ControlFlow::Exit;
+    }
+    let params = func.params.iter().map(|param| &param.val.1);
+    let mut checked = params
+        .zip(&args)
+        .try_for_each(|(typ, arg)| bindings.infer(env, &typ.val, &arg.val, arg.span));
+    for (name, value) in &named_args {
+        if let (Ok(()), Some((typ, _))) = (&checked, func.named_params.get(&name.val)) {
+            checked = bindings.infer(env, &typ.val, value, name.span);
+        }
+    }
+    if let Err(diagnostic) = checked {
+        diagnostic.emit(env.emitter.clone());
+        return ControlFlow::Exit;
+    }
+
+    env.push_type_args(bindings.types().clone());
+    let args = args.into_iter().map(|arg| arg.val).collect();
+    let result = call_function(env, func, module, args, named_args, span);
+    env.pop_type_args();
+
+    if let ControlFlow::None(value) | ControlFlow::Return(value) = &result {
+        let return_type = &func.return_type;
+        if let Err(diagnostic) = bindings.infer(env, &return_type.val, value, span) {
+            diagnostic
+                .with_child(
+                    return_type.span,
+                    Level::Note,
+                    "Expected because of the return type",
+                )
+                .emit(env.emitter.clone());
+            return ControlFlow::Exit;
+        }
+    }
+    result
+}
+
+/// Evaluates `array[index]`
+fn eval_subscript(env: &mut Env, lhs: Spanned<Expr>, index: Spanned<Expr>) -> ControlFlow {
+    let lhs_span = lhs.span;
+    let index_span = index.span;
+    let val = default_flow!(lhs.eval(env));
+    let index = default_flow!(index.eval(env));
+    let Value::Array(mut items) = val else {
+        Diagnostic::spanned(
+            lhs_span,
+            Level::Error,
+            format!("Unable to index into '{val}'"),
+        )
+        .emit(env.emitter.clone());
+        return ControlFlow::Exit;
+    };
+    match array_index(&index, items.len()) {
+        Some(index) => ControlFlow::None(items.swap_remove(index)),
+        None => {
+            index_error(index_span, &index, items.len()).emit(env.emitter.clone());
+            ControlFlow::Exit
+        }
+    }
+}
+
+/// The position of an element in an array of the length, if the index is in bounds
+fn array_index(index: &Value, len: usize) -> Option<usize> {
+    match index {
+        Value::Int(index) => usize::try_from(*index).ok().filter(|index| *index < len),
+        Value::SizedInt(index, _) => usize::try_from(*index).ok().filter(|index| *index < len),
+        _ => None,
+    }
+}
+
+fn index_error(span: Span, index: &Value, len: usize) -> Diagnostic {
+    let message = match index {
+        Value::Int(_) | Value::SizedInt(..) => {
+            format!("Index {index} is out of bounds for an array of length {len}")
+        }
+        other => format!("Arrays can only be indexed with integers, found '{other}'"),
+    };
+    Diagnostic::spanned(span, Level::Error, message)
+}
+
+/// Evaluates `[value; length]`
+fn eval_array_with_repeat(
+    env: &mut Env,
+    value: Spanned<Expr>,
+    length: Spanned<Expr>,
+) -> ControlFlow {
+    let value = default_flow!(value.eval(env));
+    let length_span = length.span;
+    match default_flow!(length.eval(env)) {
+        Value::Int(length) if length >= 0 => {
+            ControlFlow::None(Value::Array(vec![value; length as usize]))
+        }
+        other => {
+            Diagnostic::spanned(
+                length_span,
+                Level::Error,
+                format!("The length of an array has to be a positive integer, found '{other}'"),
+            )
+            .emit(env.emitter.clone());
+            ControlFlow::Exit
+        }
+    }
+}
+
+/// Evaluates `and` and `or`, which only evaluate the right side if the left doesn't decide the
+/// result already
+fn eval_logical(
+    env: &mut Env,
+    op: BinaryOp,
+    lhs: Spanned<Expr>,
+    rhs: Spanned<Expr>,
+) -> ControlFlow {
+    let short_circuit = op == BinaryOp::Or;
+    for operand in [lhs, rhs] {
+        let span = operand.span;
+        match default_flow!(operand.eval(env)) {
+            Value::Bool(val) if val == short_circuit => return ControlFlow::None(Value::Bool(val)),
+            Value::Bool(_) => (),
+            other => {
+                let op = if short_circuit { "or" } else { "and" };
+                Diagnostic::spanned(
+                    span,
+                    Level::Error,
+                    format!("The operands of `{op}` have to be booleans, found '{other}'"),
+                )
+                .emit(env.emitter.clone());
+                return ControlFlow::Exit;
+            }
+        }
+    }
+    ControlFlow::None(Value::Bool(!short_circuit))
+}
+
+fn eval_block(
+    env: &mut Env,
+    stmts: Vec<Spanned<Stmt>>,
+    final_expr: Option<Box<Spanned<Expr>>>,
+) -> ControlFlow {
+    for stmt in stmts {
+        default_flow!(stmt.eval(env));
+    }
+    match final_expr {
+        Some(expr) => expr.eval(env),
+        None => ControlFlow::None(Value::Unit),
+    }
+}
+
+/// Evaluates a labelled loop or block, which can be the target of `break` and `continue`
+fn eval_labelled(env: &mut Env, label: String, inner: Spanned<Expr>) -> ControlFlow {
+    let label = Some(label.as_str());
+    match inner.val {
+        Expr::Loop(body) => eval_loop(env, label, None, *body),
+        Expr::While(cond, body) => eval_loop(env, label, Some(*cond), *body),
+        Expr::For(pattern, iter, body) => eval_for(env, label, pattern, *iter, *body),
+        _ => match inner.eval(env) {
+            ControlFlow::Break(Some(target), inner) if Some(target.as_str()) == label => {
+                ControlFlow::None(inner)
+            }
+            control_flow => control_flow,
+        },
+    }
+}
+
+/// Whether a `break` or `continue` with the target unwinds to the loop with the label
+fn is_target(target: &Option<String>, label: Option<&str>) -> bool {
+    target.is_none() || target.as_deref() == label
+}
+
+/// Evaluates `loop` and `while` loops, which run while the condition is true
+fn eval_loop(
+    env: &mut Env,
+    label: Option<&str>,
+    cond: Option<Spanned<Expr>>,
+    body: Spanned<Expr>,
+) -> ControlFlow {
+    loop {
+        if let Some(cond) = &cond {
+            match default_flow!(cond.clone().eval(env)) {
+                Value::Bool(true) => (),
+                Value::Bool(false) => return ControlFlow::None(Value::Unit),
+                other => {
+                    Diagnostic::spanned(
+                        cond.span,
+                        Level::Error,
+                        format!("Loop conditions have to be a boolean, found '{other}'"),
+                    )
+                    .emit(env.emitter.clone());
+                    return ControlFlow::Exit;
+                }
+            }
+        }
+        match body.clone().eval(env) {
+            ControlFlow::None(_) => (),
+            ControlFlow::Continue(target) if is_target(&target, label) => (),
+            ControlFlow::Break(target, inner) if is_target(&target, label) => {
+                return ControlFlow::None(inner)
+            }
+            control_flow => return control_flow,
+        }
+    }
+}
+
+fn eval_for(
+    env: &mut Env,
+    label: Option<&str>,
+    pattern: Spanned<Pattern>,
+    iter: Spanned<Expr>,
+    body: Spanned<Expr>,
+) -> ControlFlow {
+    let iter_span = iter.span;
+    let val = default_flow!(iter.eval(env));
+    let Some(values) = iterate(&val) else {
+        Diagnostic::spanned(
+            iter_span,
+            Level::Error,
+            format!("Unable to iterate over '{val}'"),
+        )
+        .with_child(
+            vec![],
+            Level::Note,
+            "Only ranges of integers, strings and arrays can be iterated",
+        )
+        .emit(env.emitter.clone());
+        return ControlFlow::Exit;
+    };
+
+    for val in values {
+        let mut bindings = vec![];
+        if !match_pattern(env, &pattern.val, &val, &mut bindings) {
+            Diagnostic::spanned(
+                pattern.span,
+                Level::Error,
+                format!("The pattern does not match the value '{val}'"),
+            )
+            .emit(env.emitter.clone());
+            return ControlFlow::Exit;
+        }
+
+        env.push_scope(ScopeKind::Expr);
+        for (name, val) in bindings {
+            env.create(name, VariableKind::Let, val);
+        }
+        let result = body.clone().eval(env);
+        env.pop_scope();
+        match result {
+            ControlFlow::None(_) => (),
+            ControlFlow::Continue(target) if is_target(&target, label) => (),
+            ControlFlow::Break(target, inner) if is_target(&target, label) => {
+                return ControlFlow::None(inner)
+            }
+            control_flow => return control_flow,
+        }
+    }
+    ControlFlow::None(Value::Unit)
+}
+
+/// The values of integer ranges, strings and arrays, which can be used in `for` loops
+fn iterate(val: &Value) -> Option<Box<dyn Iterator<Item = Value>>> {
+    match val {
+        Value::String(inner) => Some(Box::new(
+            inner
+                .chars()
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(Value::Char),
+        )),
+        Value::Array(items) => Some(Box::new(items.clone().into_iter())),
+        // Ranges are structs without a definition
+        Value::Struct(name, fields) => {
+            let start = fields.get("start")?;
+            let (start, end, typ) = match (name.as_str(), fields.get("end")) {
+                ("Range", Some(end)) => range_bounds(start, end, false)?,
+                ("RangeInclusive", Some(end)) => range_bounds(start, end, true)?,
+                ("RangeFrom", None) => {
+                    let (start, _, typ) = range_bounds(start, start, true)?;
+                    // Stops at the largest value of the type instead of overflowing
+                    (start, NumberSuffix::int_range(typ).1, typ)
+                }
+                _ => return None,
+            };
+            Some(Box::new((start..=end).map(move |int| match typ {
+                Some(typ) => Value::SizedInt(int, typ),
+                None => Value::Int(int as i64),
+            })))
+        }
+        _ => None,
+    }
+}
+
+/// The first and last value and the integer type of a range,
+/// where integers without a suffix take the type of the other bound
+fn range_bounds(
+    start: &Value,
+    end: &Value,
+    inclusive: bool,
+) -> Option<(i128, i128, Option<NumberSuffix>)> {
+    let (start, end, typ) = match (start, end) {
+        (Value::Int(start), Value::Int(end)) => ((*start).into(), (*end).into(), None),
+        (Value::SizedInt(start, typ), Value::Int(end)) => (*start, (*end).into(), Some(*typ)),
+        (Value::Int(start), Value::SizedInt(end, typ)) => ((*start).into(), *end, Some(*typ)),
+        (Value::SizedInt(start, start_typ), Value::SizedInt(end, end_typ))
+            if start_typ == end_typ =>
+        {
+            (*start, *end, Some(*start_typ))
+        }
+        _ => return None,
+    };
+    if matches!(typ, Some(NumberSuffix::Float(_))) {
+        return None;
+    }
+    let (min, max) = NumberSuffix::int_range(typ);
+    if !(min..=max).contains(&start) || !(min..=max).contains(&end) {
+        return None;
+    }
+    Some((start, if inclusive { end } else { end - 1 }, typ))
+}
+
+/// Evaluates the body of the first arm, whose pattern matches and whose guard is true
+fn eval_match(env: &mut Env, scrutinee: Spanned<Expr>, arms: Vec<MatchArm>) -> ControlFlow {
+    let scrutinee_span = scrutinee.span;
+    let val = default_flow!(scrutinee.eval(env));
+    for (pattern, guard, body) in arms {
+        let mut bindings = vec![];
+        if !match_pattern(env, &pattern.val, &val, &mut bindings) {
+            continue;
+        }
+
+        env.push_scope(ScopeKind::Expr);
+        for (name, val) in bindings {
+            env.create(name, VariableKind::Let, val);
+        }
+        let result = match guard.map(|guard| (guard.span, guard.eval(env))) {
+            None | Some((_, ControlFlow::None(Value::Bool(true)))) => Some(body.eval(env)),
+            Some((_, ControlFlow::None(Value::Bool(false)))) => None,
+            Some((guard_span, ControlFlow::None(other))) => {
+                Diagnostic::spanned(
+                    guard_span,
+                    Level::Error,
+                    format!("Match guards have to be a boolean, found '{other}'"),
+                )
+                .emit(env.emitter.clone());
+                Some(ControlFlow::Exit)
+            }
+            Some((_, control_flow)) => Some(control_flow),
+        };
+        env.pop_scope();
+        if let Some(control_flow) = result {
+            return control_flow;
+        }
+    }
+
+    Diagnostic::spanned(
+        scrutinee_span,
+        Level::Error,
+        format!("No pattern matches the value '{val}'"),
+    )
+    .emit(env.emitter.clone());
+    ControlFlow::Exit
+}
+
+/// Checks if `val` matches the pattern and collects the values of its bindings
+fn match_pattern(
+    env: &Env,
+    pattern: &Pattern,
+    val: &Value,
+    bindings: &mut Vec<(String, Value)>,
+) -> bool {
+    match (pattern, val) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Literal(lit), val) => &Value::from(lit.clone()) == val,
+        (Pattern::Binding(name), val) => {
+            bindings.push((name.clone(), val.clone()));
+            true
+        }
+        (Pattern::At(inner, name), val) => {
+            let is_match = match_pattern(env, &inner.val, val, bindings);
+            if is_match {
+                bindings.push((name.val.clone(), val.clone()));
+            }
+            is_match
+        }
+        (Pattern::Or(alternatives), val) => alternatives.iter().any(|alternative| {
+            let len = bindings.len();
+            let is_match = match_pattern(env, &alternative.val, val, bindings);
+            if !is_match {
+                // Drop bindings of a partial match
+                bindings.truncate(len);
+            }
+            is_match
+        }),
+        // Named tuples are matched by the position of their fields
+        (Pattern::Tuple(patterns), Value::Tuple(fields)) => {
+            patterns.len() == fields.len()
+                && patterns
+                    .iter()
+                    .zip(fields)
+                    .all(|(pattern, (_, val))| match_pattern(env, &pattern.val, val, bindings))
+        }
+        (Pattern::Struct(path, fields), Value::Struct(typ, values)) => {
+            let Some(path_typ) = path.parts.last() else {
+                return false;
+            };
+            env.resolve_self(path_typ) == typ
+                && fields.iter().all(|(name, pattern)| {
+                    values
+                        .get(&name.val)
+                        .is_some_and(|val| match_pattern(env, &pattern.val, val, bindings))
+                })
+        }
+        (Pattern::EnumVariant(path, inner), Value::Enum(typ, variant, val)) => {
+            let [path_typ, path_variant] = &path.parts[..] else {
+                return false;
+            };
+            env.resolve_self(path_typ) == typ
+                && path_variant == variant
+                && match (inner, val) {
+                    (None, None) => true,
+                    (Some(inner), Some(val)) => match_pattern(env, &inner.val, val, bindings),
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+/// Variant of a user defined enum, found by [`find_variant`]
+struct Variant {
+    /// Name of the enum
+    typ: String,
+    /// Type of the value the variant holds
+    inner: Option<Spanned<Type>>,
+    /// Generic parameters of the enum, bound to the arguments of `Enum[A].variant`
+    generics: Bindings,
+}
+
+/// Finds the variant for `Enum.variant` expressions and reports unknown variants
+fn find_variant(
+    env: &Env,
+    lhs: &Spanned<Expr>,
+    variant: &str,
+    span: Span,
+) -> Result<Option<Variant>, ()> {
+    let (lhs, generic_args) = split_generic_args(lhs);
+    let Expr::Ident(name) = &lhs.val else {
+        return Ok(None);
+    };
+    // Variables shadow types
+    if env.get(name).is_some() {
+        return Ok(None);
+    }
+    let Some(Spanned {
+        val: Type::Enum(generics, variants),
+        span: definition_span,
+    }) = env.get_type(name)
+    else {
+        return Ok(None);
+    };
+
+    match variants.iter().find(|(def_name, _)| def_name == variant) {
+        Some((_, inner)) => match Bindings::explicit(env, name, generics, generic_args) {
+            Ok(generics) => Ok(Some(Variant {
+                typ: env.resolve_self(name).to_string(),
+                inner: inner.clone(),
+                generics,
+            })),
+            Err(diagnostic) => {
+                diagnostic.emit(env.emitter.clone());
+                Err(())
+            }
+        },
+        None => {
+            Diagnostic::new(
+                Level::Error,
+                format!("Enum `{name}` has no variant `{variant}`"),
+            )
+            .with_child(span, Level::Error, "Unknown variant")
+            .with_child(
+                *definition_span,
+                Level::Note,
+                format!("`{name}` is defined here"),
+            )
+            .emit(env.emitter.clone());
+            Err(())
+        }
+    }
+}
+
+/// Associated constant or function `Type.key`, found by [`find_associated`]
+struct Associated {
+    /// Name of the type
+    typ: String,
+    value: Value,
+    /// Generic arguments of the type, given like `Type[A].key`
+    generic_args: Vec<Spanned<Type>>,
+}
+
+/// Finds the associated constant or function for `Type.key` expressions
+/// and reports associated items which are not visible
+fn find_associated(
+    env: &Env,
+    lhs: &Spanned<Expr>,
+    key: &str,
+    span: Span,
+) -> Result<Option<Associated>, ()> {
+    let (lhs, generic_args) = split_generic_args(lhs);
+    let Expr::Ident(name) = &lhs.val else {
+        return Ok(None);
+    };
+    // Variables shadow types
+    if env.get(name).is_some() {
+        return Ok(None);
+    }
+    let Some(value) = env.get_associated(name, key) else {
+        return Ok(None);
+    };
+    if let Err(diagnostic) = env.check_associated_access(name, key, span) {
+        diagnostic.emit(env.emitter.clone());
+        return Err(());
+    }
+    let generics = match env.get_type(name) {
+        Some(Spanned {
+            val: Type::Struct(generics, _) | Type::Enum(generics, _),
+            ..
+        }) => &generics[..],
+        _ => &[],
+    };
+    if let Err(diagnostic) = Bindings::explicit(env, name, generics, generic_args) {
+        diagnostic.emit(env.emitter.clone());
+        return Err(());
+    }
+    Ok(Some(Associated {
+        typ: env.resolve_self(name).to_string(),
+        value: value.clone(),
+        generic_args: generic_args.to_vec(),
+    }))
+}
+
+/// Splits `Type[A, B]` into the type and its generic arguments
+fn split_generic_args(expr: &Spanned<Expr>) -> (&Spanned<Expr>, &[Spanned<Type>]) {
+    match &expr.val {
+        Expr::Generic(inner, generic_args) => (inner, generic_args),
+        _ => (expr, &[]),
+    }
+}
+
+/// Callee of `receiver.key(..)`
+struct Method {
+    callee: Value,
+    /// Whether the receiver is passed as first argument
+    pass_receiver: bool,
+    /// Type of the receiver, when calling one of its associated functions
+    self_type: Option<String>,
+}
+
+/// Resolves the callee of `receiver.key(..)`, which is either a field of the receiver,
+/// a method of its type or a function in scope, that is called with the receiver as first argument.
+fn find_method(env: &Env, receiver: &Value, key: &str, span: Span) -> Result<Method, ()> {
+    // Functions of modules are called like `module.function(..)`, not as methods
+    if let Value::Module(name, module) = receiver {
+        if let Err(diagnostic) = env.check_access(module, key, span) {
+            diagnostic.emit(env.emitter.clone());
+            return Err(());
+        }
+        let Some(callee) = receiver.field(key) else {
+            Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!("Module `{name}` has no item `{key}`"),
+            )
+            .emit(env.emitter.clone());
+            return Err(());
+        };
+        return Ok(Method {
+            callee: callee.clone(),
+            pass_receiver: false,
+            self_type: None,
+        });
+    }
+    let typ = receiver.type_name();
+    let method = typ
+        .and_then(|typ| env.get_associated(typ, key))
+        .map(|callee| (callee, typ));
+    let function = env
+        .get(key)
+        .filter(|value| matches!(value, Value::Function(..) | Value::NativeFunction(_)))
+        .map(|callee| (callee, None));
+
+    match (receiver.field(key), method.or(function)) {
+        (Some(field), None) => Ok(Method {
+            callee: field.clone(),
+            pass_receiver: false,
+            self_type: None,
+        }),
+        (None, Some((callee, Some(typ)))) => {
+            if let Err(diagnostic) = env.check_associated_access(typ, key, span) {
+                diagnostic.emit(env.emitter.clone());
+                return Err(());
+            }
+            if let Value::Function(func, _) = callee {
+                if func.params.first().map(|param| param.val.0.as_str()) != Some("self") {
+                    Diagnostic::spanned(
+                        span,
+                        Level::Error,
+                        format!("`{typ}.{key}` is not a method, it has no `self` parameter"),
+                    )
+                    .with_child(
+                        vec![],
+                        Level::Help,
+                        format!("Call it on the type instead, like `{typ}.{key}(..)`"),
+                    )
+                    .emit(env.emitter.clone());
+                    return Err(());
+                }
+            }
+            Ok(Method {
+                callee: callee.clone(),
+                pass_receiver: true,
+                self_type: Some(typ.to_string()),
+            })
+        }
+        (None, Some((callee, None))) => Ok(Method {
+            callee: callee.clone(),
+            pass_receiver: true,
+            self_type: None,
+        }),
+        (Some(_), Some((_, typ))) => {
+            let function = match typ {
+                Some(typ) => format!("{typ}.{key}"),
+                None => key.to_string(),
+            };
+            Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!("Call to `{key}` is ambiguous, it is both a field and a function"),
+            )
+            .with_child(
+                vec![],
+                Level::Help,
+                format!(
+                    "Use `{function}(value, ..)` to call the function \
+                     or `const {key} = value.{key}` to call the field"
+                ),
+            )
+            .emit(env.emitter.clone());
+            Err(())
+        }
+        (None, None) => {
+            Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!("Neither a field nor a function '{key}' exists for value '{receiver}'"),
+            )
+            .emit(env.emitter.clone());
+            Err(())
+        }
+    }
+}
+
+/// Reports unknown struct types and unknown and missing fields.
+/// Returns the default values of the missing fields.
+fn check_struct_fields(
+    env: &Env,
+    path: &Path,
+    fields: &[(Spanned<String>, Spanned<Expr>)],
+    span: Span,
+) -> Result<Vec<(String, Value)>, ()> {
+    let name = path.to_string();
+    let (definition, definition_span) = match env.get_type_at(path, span) {
+        Ok(Some(Spanned {
+            val: Type::Struct(_, definition),
+            span,
+        })) => (definition, span),
+        // Ranges are built without a definition
+        Ok(None)
+            if matches!(
+                name.as_str(),
+                "Range" | "RangeInclusive" | "RangeFrom" | "RangeTo"
+            ) =>
+        {
+            return Ok(vec![])
+        }
+        Ok(None) => {
+            Diagnostic::spanned(span, Level::Error, format!("Unknown struct type `{name}`"))
+                .with_child(span, Level::Error, format!("`{name}` is not defined"))
+                .emit(env.emitter.clone());
+            return Err(());
+        }
+        Ok(Some(other)) => {
+            Diagnostic::spanned(span, Level::Error, format!("`{name}` is not a struct type"))
+                .with_child(other.span, Level::Note, format!("`{name}` is defined here"))
+                .emit(env.emitter.clone());
+            return Err(());
+        }
+        Err(diagnostic) => {
+            diagnostic.emit(env.emitter.clone());
+            return Err(());
+        }
+    };
+
+    let mut valid = true;
+    for (field, _) in fields {
+        if definition
+            .iter()
+            .all(|(def_name, ..)| def_name != &field.val)
+        {
+            Diagnostic::new(
+                Level::Error,
+                format!("Struct `{name}` has no field `{field}`"),
+            )
+            .with_child(field.span, Level::Error, "Unknown field")
+            .with_child(
+                *definition_span,
+                Level::Note,
+                format!("`{name}` is defined here"),
+            )
+            .emit(env.emitter.clone());
+            valid = false;
+        }
+    }
+
+    let mut missing = vec![];
+    let mut defaults = vec![];
+    for (def_name, _, default, _) in definition {
+        if fields.iter().any(|(field, _)| &field.val == def_name) {
+            continue;
+        }
+        match default {
+            Some(lit) => defaults.push((def_name.clone(), Value::from(lit.clone()))),
+            None => missing.push(format!("`{def_name}`")),
+        }
+    }
+    if !missing.is_empty() {
+        let fields = if missing.len() == 1 {
+            "field"
+        } else {
+            "fields"
+        };
+        let missing = missing.join(", ");
+        Diagnostic::new(
+            Level::Error,
+            format!("Missing {fields} {missing} in `{name}`"),
+        )
+        .with_child(span, Level::Error, format!("Missing {missing}"))
+        .with_child(
+            *definition_span,
+            Level::Note,
+            format!("`{name}` is defined here"),
+        )
+        .emit(env.emitter.clone());
+        valid = false;
+    }
+
+    if valid {
+        Ok(defaults)
+    } else {
+        Err(())
+    }
+}
+
+/// Checks the values of the fields of generic structs against the types of the fields,
+/// which binds the generic parameters that are not given explicitly like in `Box[int] { .. }`
+fn check_struct_generics(
+    env: &Env,
+    path: &Path,
+    generic_args: &[Spanned<Type>],
+    fields: &[(String, Spanned<Value>)],
+    span: Span,
+) -> Result<(), Diagnostic> {
+    let name = path.to_string();
+    // Unknown types are reported by `check_struct_fields`
+    let Ok(Some(Spanned {
+        val: Type::Struct(generics, definition),
+        ..
+    })) = env.get_type_at(path, span)
+    else {
+        return Bindings::explicit(env, &name, &[], generic_args).map(|_| ());
+    };
+    let mut bindings = Bindings::explicit(env, &name, generics, generic_args)?;
+    if !bindings.is_generic() {
+        return Ok(());
+    }
+    for (field, value) in fields {
+        if let Some((_, typ, ..)) = definition.iter().find(|(name, ..)| name == field) {
+            bindings.infer(env, &typ.val, &value.val, value.span)?;
+        }
+    }
+    Ok(())
+}
+
 fn eval_binary<T>(op: BinaryOp, lhs: T, rhs: T, make_value: fn(T) -> Value) -> Value
 where
-    T: PartialOrd + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+    T: PartialOrd
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + Rem<Output = T>,
 {
     match op {
         BinaryOp::Add => make_value(lhs + rhs),
         BinaryOp::Sub => make_value(lhs - rhs),
         BinaryOp::Mul => make_value(lhs * rhs),
         BinaryOp::Div => make_value(lhs / rhs),
+        BinaryOp::Mod => make_value(lhs % rhs),
+
+        BinaryOp::LessThan
+        | BinaryOp::LessThanEq
+        | BinaryOp::GreaterThan
+        | BinaryOp::GreaterThanEq
+        | BinaryOp::Eq
+        | BinaryOp::NotEq => eval_comparison(op, lhs, rhs),
+
+        BinaryOp::Concat
+        | BinaryOp::And
+        | BinaryOp::Or
+        | BinaryOp::Assign
+        | BinaryOp::AddAssign
+        | BinaryOp::SubAssign
+        | BinaryOp::MulAssign
+        | BinaryOp::DivAssign => {
+            unreachable!("Evaluated before the operands are matched")
+        }
+    }
+}
 
+fn eval_comparison<T: PartialOrd>(op: BinaryOp, lhs: T, rhs: T) -> Value {
+    match op {
         BinaryOp::LessThan => Value::Bool(lhs < rhs),
         BinaryOp::LessThanEq => Value::Bool(lhs <= rhs),
         BinaryOp::GreaterThan => Value::Bool(lhs > rhs),
@@ -293,14 +1884,6 @@ where
         BinaryOp::Eq => Value::Bool(lhs == rhs),
         BinaryOp::NotEq => Value::Bool(lhs != rhs),
 
-        BinaryOp::Assign => {
-            debug_todo();
-            Value::Unit
-        }
+        _ => unreachable!("Only called with comparison operators"),
     }
 }
-
-#[track_caller]
-fn debug_todo() {
-    println!("Hit TODO: {}", std::panic::Location::caller());
-}
@@ -1,16 +1,32 @@
 use super::Value;
-use crate::{ast::VariableKind, error::Diagnostic};
-use std::{collections::HashMap, sync::mpsc::Sender};
+use crate::{
+    ast::{Path, Type, VariableKind, Visibility},
+    error::{Diagnostic, Level},
+    span::{Span, Spanned},
+};
+use itertools::Itertools;
+use std::{cell::OnceCell, collections::HashMap, rc::Rc, sync::mpsc::Sender};
 
 pub struct Env {
-    scopes: Vec<Scope>,
+    scopes: Vec<Frame>,
+    /// Modules whose items are currently being evaluated or whose functions are being called,
+    /// functions created there can access the items of the last one
+    modules: Vec<ModuleScope>,
+    /// Types whose associated functions are currently being called, `Self` refers to the last one
+    self_types: Vec<String>,
+    /// Types of the generic parameters of the functions that are currently being called,
+    /// only the last ones are visible
+    type_args: Vec<HashMap<String, Spanned<Type>>>,
     pub emitter: Sender<Diagnostic>,
 }
 
 impl Env {
     pub fn new(sender: Sender<Diagnostic>) -> Self {
         Self {
-            scopes: vec![Scope::new(ScopeKind::Module)],
+            scopes: vec![Frame::Local(Scope::new(ScopeKind::Module))],
+            modules: vec![],
+            self_types: vec![],
+            type_args: vec![],
             emitter: sender,
         }
     }
@@ -26,16 +42,182 @@ impl Env {
     }
 
     pub fn push_scope(&mut self, kind: ScopeKind) {
-        self.scopes.push(Scope::new(kind));
+        self.scopes.push(Frame::Local(Scope::new(kind)));
     }
 
     pub fn pop_scope(&mut self) {
         self.scopes.pop();
     }
 
+    /// Starts evaluating the items of a new module, [`Env::pop_module`] finishes it
+    pub fn push_module(&mut self) -> ModuleScope {
+        let module = ModuleScope(Rc::new(ModuleData {
+            parent: self.current_module(),
+            scope: OnceCell::new(),
+        }));
+        self.scopes
+            .push(Frame::Local(Scope::new(ScopeKind::Module)));
+        self.modules.push(module.clone());
+        module
+    }
+
+    /// Finishes the module started by [`Env::push_module`], which makes its items accessible
+    pub fn pop_module(&mut self) {
+        let Some(Frame::Local(scope)) = self.scopes.pop() else {
+            panic!("Internal Error: Popped a module that was not pushed");
+        };
+        let module = self
+            .modules
+            .pop()
+            .expect("Internal Error: No module to pop");
+        if module.0.scope.set(scope).is_err() {
+            panic!("Internal Error: Module was already evaluated");
+        }
+    }
+
+    /// Enters the module of a function that is being called.
+    /// Returns false for modules which are still being evaluated, their items are already in scope.
+    pub fn enter_module(&mut self, module: &ModuleScope) -> bool {
+        if module.0.scope.get().is_none() {
+            return false;
+        }
+        self.scopes.push(Frame::Module(module.clone()));
+        self.modules.push(module.clone());
+        true
+    }
+
+    pub fn leave_module(&mut self) {
+        self.scopes.pop();
+        self.modules.pop();
+    }
+
+    /// The module in which functions are currently created, `None` for the root module
+    pub fn current_module(&self) -> Option<ModuleScope> {
+        self.modules.last().cloned()
+    }
+
+    /// Scope of the module around the one the code is written in, the root module has none
+    fn super_module(&self) -> Option<&Scope> {
+        let Some(parent) = &self.modules.last()?.0.parent else {
+            return Some(self.package());
+        };
+        if let Some(scope) = parent.0.scope.get() {
+            return Some(scope);
+        }
+        // The parent is still being evaluated, after the root module each module has a frame
+        self.scopes[1..]
+            .iter()
+            .filter(|frame| frame.kind() == ScopeKind::Module)
+            .skip(1)
+            .zip(&self.modules)
+            .find(|(_, module)| *module == parent)
+            .map(|(frame, _)| frame.scope())
+    }
+
+    /// Scope of the root module, which is evaluated in the scope after the prelude
+    fn package(&self) -> &Scope {
+        self.scopes
+            .iter()
+            .skip(1)
+            .find(|frame| frame.kind() == ScopeKind::Module)
+            .unwrap_or(&self.scopes[0])
+            .scope()
+    }
+
+    /// Copies the items of `use path.{items}` into the current scope
+    pub fn use_items(
+        &mut self,
+        path: &[Spanned<String>],
+        items: &[Spanned<String>],
+    ) -> Result<(), Diagnostic> {
+        let scope = self.module_at(path)?;
+        let mut found = vec![];
+        for item in items {
+            let declaration = scope.declarations.get(&item.val);
+            self.check_declaration(&item.val, declaration, item.span)?;
+            let var = scope.vars.get(&item.val).map(|(_, value)| value.clone());
+            let typ = scope.types.get(&item.val).cloned();
+            let associated = scope.associated.get(&item.val).cloned();
+            if var.is_none() && typ.is_none() {
+                let module = path.iter().map(|segment| segment.val.as_str()).join(".");
+                return Err(Diagnostic::spanned(
+                    item.span,
+                    Level::Error,
+                    format!("Module `{module}` has no item `{}`", item.val),
+                ));
+            }
+            found.push((item.val.clone(), var, typ, associated));
+        }
+
+        let current = self.current_scope();
+        for (name, var, typ, associated) in found {
+            if let Some(value) = var {
+                current
+                    .vars
+                    .insert(name.clone(), (VariableKind::Const, value));
+            }
+            if let Some(typ) = typ {
+                current.types.insert(name.clone(), typ);
+            }
+            if let Some(associated) = associated {
+                current.associated.insert(name, associated);
+            }
+        }
+        Ok(())
+    }
+
+    /// Definition of the type at `path`, like `Point` or `geometry.Point`
+    pub fn get_type_at(
+        &self,
+        path: &Path,
+        span: Span,
+    ) -> Result<Option<&Spanned<Type>>, Diagnostic> {
+        let (name, modules) = path
+            .parts
+            .split_last()
+            .expect("Internal Error: Empty paths are reported while parsing");
+        if modules.is_empty() {
+            return Ok(self.get_type(name));
+        }
+        let modules: Vec<_> = modules
+            .iter()
+            .map(|module| Spanned::new(module.clone(), span))
+            .collect();
+        let scope = self.module_at(&modules)?;
+        self.check_declaration(name, scope.declarations.get(name), span)?;
+        Ok(scope.types.get(name))
+    }
+
+    /// Scope of the module at `path`, which starts with a module in scope, `super` or `package`
+    fn module_at(&self, path: &[Spanned<String>]) -> Result<&Scope, Diagnostic> {
+        let (first, rest) = path
+            .split_first()
+            .expect("Internal Error: Empty paths are reported while parsing");
+        let mut scope = match first.val.as_str() {
+            "super" => self.super_module().ok_or_else(|| {
+                Diagnostic::spanned(
+                    first.span,
+                    Level::Error,
+                    "The root module has no `super` module",
+                )
+            })?,
+            "package" => self.package(),
+            _ => module_scope(self.get(&first.val), first)?.scope(),
+        };
+        for segment in rest {
+            let value = scope.vars.get(&segment.val).map(|(_, value)| value);
+            let module = module_scope(value, segment)?;
+            let declaration = scope.declarations.get(&segment.val);
+            self.check_declaration(&segment.val, declaration, segment.span)?;
+            scope = module.scope();
+        }
+        Ok(scope)
+    }
+
     pub fn variables(&self) -> Vec<Vec<(String, (VariableKind, Value))>> {
         self.scopes
             .iter()
+            .map(Frame::scope)
             .map(|scope| {
                 // TODO Too many clones
                 let mut vars: Vec<_> = scope.vars.clone().into_iter().collect();
@@ -46,59 +228,339 @@ impl Env {
     }
 
     pub fn create(&mut self, name: impl Into<String>, kind: VariableKind, value: Value) {
-        self.scopes
-            .last_mut()
-            .unwrap()
-            .vars
-            .insert(name.into(), (kind, value));
+        self.current_scope().vars.insert(name.into(), (kind, value));
     }
 
-    pub fn assign(&mut self, name: &str, value: Value) {
-        let Some((kind, value_mut)) = self.scopes.last_mut().unwrap().vars.get_mut(name) else {
-            todo!()
-        };
-        match kind {
-            VariableKind::Const => todo!("Cannot assign to const"),
-            VariableKind::Let => todo!("Cannot assign to let"),
-            VariableKind::LetMut => *value_mut = value,
+    /// The kind of the variable, to check whether it may be assigned to
+    pub fn get_kind(&self, name: &str) -> Option<VariableKind> {
+        self.visible_scopes()
+            .find_map(|scope| scope.vars.get(name))
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Items of modules entered by calling their functions can't be borrowed mutably,
+    /// which is fine, because they are all constants
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        let index = self
+            .visible_scope_indices()
+            .find(|index| self.scopes[*index].scope().vars.contains_key(name))?;
+        match &mut self.scopes[index] {
+            Frame::Local(scope) => scope.vars.get_mut(name).map(|(_, value)| value),
+            Frame::Module(_) => None,
         }
     }
 
     pub fn get(&self, name: &str) -> Option<&Value> {
-        // search local scopes from inner-most outwards
-        let mut prev_kind = ScopeKind::Expr;
-        for scope in self.scopes.iter().rev() {
-            // jump out of nested function scopes,
-            // closures are not yet supported
-            if prev_kind == ScopeKind::Function && scope.kind == ScopeKind::Function {
-                continue;
-            }
+        self.visible_scopes()
+            .find_map(|scope| scope.vars.get(name))
+            .map(|(_, value)| value)
+    }
+
+    pub fn create_type(&mut self, name: impl Into<String>, typ: Spanned<Type>) {
+        self.current_scope().types.insert(name.into(), typ);
+    }
+
+    pub fn get_type(&self, name: &str) -> Option<&Spanned<Type>> {
+        let name = self.resolve_self(name);
+        self.visible_scopes()
+            .find_map(|scope| scope.types.get(name))
+    }
+
+    pub fn create_associated(
+        &mut self,
+        typ: impl Into<String>,
+        name: Spanned<String>,
+        visibility: Visibility,
+        value: Value,
+    ) {
+        let declaration = self.declaration(visibility, name.span);
+        self.current_scope()
+            .associated
+            .entry(typ.into())
+            .or_default()
+            .insert(name.val, (value, declaration));
+    }
+
+    /// Associated constant or function `name` of the type `typ`
+    pub fn get_associated(&self, typ: &str, name: &str) -> Option<&Value> {
+        self.find_associated(typ, name).map(|(value, _)| value)
+    }
+
+    fn find_associated(&self, typ: &str, name: &str) -> Option<&(Value, Declaration)> {
+        let typ = self.resolve_self(typ);
+        self.visible_scopes()
+            .find_map(|scope| scope.associated.get(typ)?.get(name))
+    }
+
+    /// Records the visibility of an item of the current module
+    pub fn declare(&mut self, name: &Spanned<String>, visibility: Visibility) {
+        let declaration = self.declaration(visibility, name.span);
+        self.current_scope()
+            .declarations
+            .insert(name.val.clone(), declaration);
+    }
+
+    fn declaration(&self, visibility: Visibility, span: Span) -> Declaration {
+        Declaration {
+            visibility,
+            span,
+            module: self.current_module(),
+        }
+    }
 
-            if let Some((_, value)) = scope.vars.get(name) {
-                return Some(value);
+    /// Reports using the item `name` of `module` where it is not visible
+    pub fn check_access(
+        &self,
+        module: &ModuleScope,
+        name: &str,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
+        self.check_declaration(name, module.scope().declarations.get(name), span)
+    }
+
+    /// Reports using the associated constant or function `typ.name` where it is not visible
+    pub fn check_associated_access(
+        &self,
+        typ: &str,
+        name: &str,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
+        let declaration = self
+            .find_associated(typ, name)
+            .map(|(_, declaration)| declaration);
+        self.check_declaration(name, declaration, span)
+    }
+
+    fn check_declaration(
+        &self,
+        name: &str,
+        declaration: Option<&Declaration>,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
+        // Items of the prelude and items imported with `use` have no declaration
+        let Some(declaration) = declaration else {
+            return Ok(());
+        };
+        // The module an item is visible in includes all of its sub modules
+        let (visible_in, message, help) = match declaration.visibility {
+            Visibility::Public | Visibility::Package => return Ok(()),
+            Visibility::Private => (
+                declaration.module.as_ref(),
+                format!("`{name}` is private"),
+                "Declare it with `pub` to use it outside of its module",
+            ),
+            Visibility::Super => (
+                declaration
+                    .module
+                    .as_ref()
+                    .and_then(|module| module.0.parent.as_ref()),
+                format!("`{name}` is only visible in the parent module"),
+                "Declare it with `pub` to use it everywhere",
+            ),
+        };
+        // Everything is inside of the root module
+        let Some(visible_in) = visible_in else {
+            return Ok(());
+        };
+        let mut current = self.current_module();
+        while let Some(module) = current {
+            if module == *visible_in {
+                return Ok(());
             }
+            current = module.0.parent.clone();
+        }
+
+        Err(Diagnostic::spanned(span, Level::Error, message)
+            .with_child(span, Level::Error, "Not visible here")
+            .with_child(
+                declaration.span,
+                Level::Note,
+                format!("`{name}` is declared here"),
+            )
+            .with_child(vec![], Level::Help, help))
+    }
+
+    pub fn push_self_type(&mut self, typ: String) {
+        self.self_types.push(typ);
+    }
+
+    pub fn pop_self_type(&mut self) {
+        self.self_types.pop();
+    }
+
+    pub fn push_type_args(&mut self, args: HashMap<String, Spanned<Type>>) {
+        self.type_args.push(args);
+    }
+
+    pub fn pop_type_args(&mut self) {
+        self.type_args.pop();
+    }
+
+    /// Type of the generic parameter `name` of the function that is currently being called
+    pub fn get_type_arg(&self, name: &str) -> Option<&Spanned<Type>> {
+        self.type_args.last()?.get(name)
+    }
+
+    /// Replaces `Self` with the type it currently refers to
+    pub fn resolve_self<'a>(&'a self, typ: &'a str) -> &'a str {
+        match (typ, self.self_types.last()) {
+            ("Self", Some(self_type)) => self_type,
+            _ => typ,
+        }
+    }
+
+    fn current_scope(&mut self) -> &mut Scope {
+        match self.scopes.last_mut() {
+            Some(Frame::Local(scope)) => scope,
+            _ => panic!("Internal Error: Unable to create items in an evaluated module"),
+        }
+    }
+
+    /// Local scopes from inner-most outwards
+    fn visible_scopes(&self) -> impl Iterator<Item = &Scope> {
+        self.visible_scope_indices()
+            .map(|index| self.scopes[index].scope())
+    }
+
+    fn visible_scope_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut prev_kind = ScopeKind::Expr;
+        self.scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(move |(index, frame)| {
+                // Items of outer modules have to be imported with `use`,
+                // only the prelude is always visible
+                if prev_kind == ScopeKind::Module && index != 0 {
+                    return None;
+                }
+                // jump out of nested function scopes,
+                // closures are not yet supported
+                if prev_kind == ScopeKind::Function && frame.kind() == ScopeKind::Function {
+                    return None;
+                }
+                prev_kind = frame.kind();
+                Some(index)
+            })
+    }
+}
+
+fn module_scope<'a>(
+    value: Option<&'a Value>,
+    name: &Spanned<String>,
+) -> Result<&'a ModuleScope, Diagnostic> {
+    match value {
+        Some(Value::Module(_, module)) => Ok(module),
+        Some(value) => Err(Diagnostic::spanned(
+            name.span,
+            Level::Error,
+            format!("`{}` is not a module", name.val),
+        )
+        .with_child(name.span, Level::Error, format!("Has the value '{value}'"))),
+        None => Err(Diagnostic::spanned(
+            name.span,
+            Level::Error,
+            format!("Unable to find the module `{}`", name.val),
+        )),
+    }
+}
+
+enum Frame {
+    Local(Scope),
+    /// Module of a function that is being called
+    Module(ModuleScope),
+}
+
+impl Frame {
+    fn scope(&self) -> &Scope {
+        match self {
+            Frame::Local(scope) => scope,
+            Frame::Module(module) => module.scope(),
+        }
+    }
 
-            prev_kind = scope.kind;
+    fn kind(&self) -> ScopeKind {
+        match self {
+            Frame::Local(scope) => scope.kind,
+            Frame::Module(_) => ScopeKind::Module,
         }
+    }
+}
 
-        // search modules
-        // for scope in self.scopes.iter() {
-        // 	// TODO
-        // }
+/// Items of a module, set once all of them are evaluated
+#[derive(Clone)]
+pub struct ModuleScope(Rc<ModuleData>);
+
+struct ModuleData {
+    /// `None` for modules inside of the root module
+    parent: Option<ModuleScope>,
+    scope: OnceCell<Scope>,
+}
 
-        None
+impl ModuleScope {
+    pub fn with_constants(constants: impl IntoIterator<Item = (&'static str, Value)>) -> Self {
+        let mut scope = Scope::new(ScopeKind::Module);
+        for (name, value) in constants {
+            scope
+                .vars
+                .insert(name.to_string(), (VariableKind::Const, value));
+        }
+        Self(Rc::new(ModuleData {
+            parent: None,
+            scope: OnceCell::from(scope),
+        }))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.scope().vars.get(name).map(|(_, value)| value)
+    }
+
+    fn scope(&self) -> &Scope {
+        self.0
+            .scope
+            .get()
+            .expect("Internal Error: Module is still being evaluated")
+    }
+}
+
+impl PartialEq for ModuleScope {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// Modules can contain functions which refer to the module itself
+impl std::fmt::Debug for ModuleScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ModuleScope")
     }
 }
 
 struct Scope {
     vars: HashMap<String, (VariableKind, Value)>,
+    types: HashMap<String, Spanned<Type>>,
+    /// Constants and functions from `impl` blocks, by the name of their type
+    associated: HashMap<String, HashMap<String, (Value, Declaration)>>,
+    /// Visibility of the items of a module, which are either variables or types
+    declarations: HashMap<String, Declaration>,
     kind: ScopeKind,
 }
 
+#[derive(Clone)]
+struct Declaration {
+    visibility: Visibility,
+    span: Span,
+    /// Module the item is declared in, `None` for the root module
+    module: Option<ModuleScope>,
+}
+
 impl Scope {
     fn new(kind: ScopeKind) -> Self {
         Self {
             vars: HashMap::new(),
+            types: HashMap::new(),
+            associated: HashMap::new(),
+            declarations: HashMap::new(),
             kind,
         }
     }
@@ -1,22 +1,58 @@
 use super::{ControlFlow, Value};
 use crate::{
     ast,
+    error::{Diagnostic, Level},
     interpret::{env::ScopeKind, Env, Interpret, VariableKind},
+    span::{Span, Spanned},
 };
 use std::fmt::Display;
 
 pub trait Call {
-    fn call(&self, env: &mut Env, args: Vec<Value>) -> ControlFlow;
+    fn call(
+        &self,
+        env: &mut Env,
+        args: Vec<Value>,
+        named_args: Vec<(Spanned<String>, Value)>,
+        span: Span,
+    ) -> ControlFlow;
 }
 
 impl Call for ast::Function {
-    fn call(&self, env: &mut Env, args: Vec<Value>) -> ControlFlow {
-        assert!(self.params.len() == args.len());
+    fn call(
+        &self,
+        env: &mut Env,
+        args: Vec<Value>,
+        mut named_args: Vec<(Spanned<String>, Value)>,
+        span: Span,
+    ) -> ControlFlow {
+        if let Err(diagnostic) = check_args(self, args.len(), &named_args, span) {
+            diagnostic.emit(env.emitter.clone());
+            return ControlFlow::Exit;
+        }
         env.push_scope(ScopeKind::Function);
 
-        for ((name, typ), arg) in self.params.iter().zip(args) {
+        for (param, arg) in self.params.iter().zip(args) {
+            let (name, typ) = &param.val;
             env.create(name.clone(), VariableKind::Let, arg)
         }
+        // Defaults only see the positional parameters, all named ones are bound afterwards
+        let mut named_values = vec![];
+        for (name, (_, default)) in &self.named_params {
+            let value = match named_args.iter().position(|(arg, _)| &arg.val == name) {
+                Some(index) => named_args.swap_remove(index).1,
+                None => match default.clone().eval(env) {
+                    ControlFlow::None(inner) => inner,
+                    control_flow => {
+                        env.pop_scope();
+                        return control_flow;
+                    }
+                },
+            };
+            named_values.push((name.clone(), value));
+        }
+        for (name, value) in named_values {
+            env.create(name, VariableKind::Let, value)
+        }
         let result = self.body.clone().eval(env);
 
         env.pop_scope();
@@ -29,28 +65,102 @@ pub enum NativeFunction {
     Params1(fn(&Value) -> Value),
     Params2(fn(&Value, &Value) -> Value),
     ParamsVar(fn(&[Value]) -> Value),
+    /// Reports the error message at the call, when the arguments are invalid
+    TryParams1(fn(&Value) -> Result<Value, String>),
 }
 
 impl Call for NativeFunction {
-    fn call(&self, _env: &mut Env, args: Vec<Value>) -> ControlFlow {
+    fn call(
+        &self,
+        env: &mut Env,
+        args: Vec<Value>,
+        named_args: Vec<(Spanned<String>, Value)>,
+        span: Span,
+    ) -> ControlFlow {
+        if let Some((name, _)) = named_args.first() {
+            Diagnostic::spanned(
+                name.span,
+                Level::Error,
+                format!(
+                    "Native functions don't take named arguments like `{}`",
+                    name.val
+                ),
+            )
+            .emit(env.emitter.clone());
+            return ControlFlow::Exit;
+        }
+        let expected = match self {
+            NativeFunction::Params1(_) | NativeFunction::TryParams1(_) => 1,
+            NativeFunction::Params2(_) => 2,
+            NativeFunction::ParamsVar(inner) => return ControlFlow::None(inner(&args)),
+        };
+        if args.len() != expected {
+            arity_error(expected, args.len(), span).emit(env.emitter.clone());
+            return ControlFlow::Exit;
+        }
         match self {
-            NativeFunction::Params1(inner) => {
-                assert!(args.len() == 1);
-                ControlFlow::None(inner(&args[0]))
-            }
-            NativeFunction::Params2(inner) => {
-                assert!(args.len() == 2);
-                ControlFlow::None(inner(&args[0], &args[1]))
-            }
+            NativeFunction::Params1(inner) => ControlFlow::None(inner(&args[0])),
+            NativeFunction::Params2(inner) => ControlFlow::None(inner(&args[0], &args[1])),
             NativeFunction::ParamsVar(inner) => ControlFlow::None(inner(&args)),
+            NativeFunction::TryParams1(inner) => match inner(&args[0]) {
+                Ok(value) => ControlFlow::None(value),
+                Err(message) => {
+                    Diagnostic::spanned(span, Level::Error, message).emit(env.emitter.clone());
+                    ControlFlow::Exit
+                }
+            },
         }
     }
 }
 
+/// Checks that every named argument has a parameter and that the number of arguments matches
+pub(super) fn check_args(
+    func: &ast::Function,
+    args: usize,
+    named_args: &[(Spanned<String>, Value)],
+    span: Span,
+) -> Result<(), Diagnostic> {
+    if let Some((name, _)) = named_args
+        .iter()
+        .find(|(name, _)| !func.named_params.contains_key(&name.val))
+    {
+        return Err(Diagnostic::spanned(
+            name.span,
+            Level::Error,
+            format!("Function has no named parameter `{}`", name.val),
+        ));
+    }
+    if args != func.params.len() {
+        return Err(arity_error(func.params.len(), args, span));
+    }
+    Ok(())
+}
+
+/// Reports a call with the wrong number of positional arguments
+fn arity_error(expected: usize, given: usize, span: Span) -> Diagnostic {
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    Diagnostic::spanned(
+        span,
+        Level::Error,
+        format!(
+            "Function takes {expected} argument{}, but {given} {} given",
+            plural(expected),
+            if given == 1 { "was" } else { "were" },
+        ),
+    )
+    .with_child(
+        span,
+        Level::Error,
+        format!("{given} argument{}", plural(given)),
+    )
+}
+
 impl Display for NativeFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            NativeFunction::Params1(_func) => f.write_str("extern fn(1)"),
+            NativeFunction::Params1(_) | NativeFunction::TryParams1(_) => {
+                f.write_str("extern fn(1)")
+            }
             NativeFunction::Params2(_func) => f.write_str("extern fn(2)"),
             NativeFunction::ParamsVar(_func) => f.write_str("extern fn(..[]TODO)"),
         }
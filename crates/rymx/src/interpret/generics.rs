@@ -0,0 +1,440 @@
+//! Instantiates generic functions and types with the types of the values they are used with.
+//! Explicit and inferred generic arguments are checked against the bounds of their parameters
+//! and against each other.
+
+use super::{Env, Value};
+use crate::{
+    ast::{ArraySize, Function, GenericParam, Literal, Path, Type},
+    error::{Diagnostic, Level},
+    span::{Span, Spanned},
+};
+use std::collections::HashMap;
+
+/// The generic parameters of a function or type, with the types they are bound to so far
+pub struct Bindings {
+    params: Vec<GenericParam>,
+    /// Spanned where the type was given or inferred from
+    types: HashMap<String, Spanned<Type>>,
+}
+
+impl Bindings {
+    /// Binds the generic parameters of `Type[A, B]` to its explicit arguments, if there are any
+    pub fn explicit(
+        env: &Env,
+        name: &str,
+        params: &[GenericParam],
+        args: &[Spanned<Type>],
+    ) -> Result<Self, Diagnostic> {
+        let mut bindings = Self::new(params);
+        if args.is_empty() {
+            return Ok(bindings);
+        }
+        if args.len() != params.len() {
+            return Err(arity_error(&format!("`{name}`"), params.len(), args));
+        }
+        for (param, arg) in params.iter().zip(args) {
+            bindings.bind(env, param, resolve_type_args(env, arg))?;
+        }
+        Ok(bindings)
+    }
+
+    /// Binds the generic parameters of a function to the explicit arguments of its type
+    /// `List[int].new` and of itself `id[int]`.
+    /// The parameters of associated functions start with the ones of their impl block.
+    pub fn for_function(
+        env: &Env,
+        func: &Function,
+        type_args: &[Spanned<Type>],
+        args: &[Spanned<Type>],
+    ) -> Result<Self, Diagnostic> {
+        let mut bindings = Self::new(&func.generics);
+        for (param, arg) in func.generics.iter().zip(type_args) {
+            bindings.bind(env, param, resolve_type_args(env, arg))?;
+        }
+        if args.is_empty() {
+            return Ok(bindings);
+        }
+        let rest = &func.generics[type_args.len().min(func.generics.len())..];
+        if args.len() != rest.len() {
+            return Err(arity_error("This function", rest.len(), args));
+        }
+        for (param, arg) in rest.iter().zip(args) {
+            bindings.bind(env, param, resolve_type_args(env, arg))?;
+        }
+        Ok(bindings)
+    }
+
+    fn new(params: &[GenericParam]) -> Self {
+        Self {
+            params: params.to_vec(),
+            types: HashMap::new(),
+        }
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.params.is_empty()
+    }
+
+    /// The types of the parameters, which are visible inside of the generic function
+    pub fn types(&self) -> &HashMap<String, Spanned<Type>> {
+        &self.types
+    }
+
+    /// Checks that `value` has the type `expected`.
+    /// The generic parameters in it are bound to the type of the value they are first used with.
+    pub fn infer(
+        &mut self,
+        env: &Env,
+        expected: &Type,
+        value: &Value,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
+        let param = match expected {
+            Type::Path(path) => self.param(path).cloned(),
+            _ => None,
+        };
+        if let Some(param) = param {
+            let found = type_of(value, span);
+            return match self.types.get(&param.name.val) {
+                Some(typ) if !fits(env, &found, &typ.val) => Err(Diagnostic::spanned(
+                    span,
+                    Level::Error,
+                    format!("Expected `{typ}` for `{}`, found `{found}`", param.name),
+                )
+                .with_child(span, Level::Error, format!("This is `{found}`"))
+                .with_child(
+                    typ.span,
+                    Level::Note,
+                    format!("`{}` is `{typ}` because of this", param.name),
+                )),
+                Some(_) => Ok(()),
+                None => self.bind(env, &param, Spanned::new(found, span)),
+            };
+        }
+
+        match (expected, value) {
+            (Type::Array(_, item_type), Value::Array(items)) => {
+                for item in items {
+                    self.infer(env, &item_type.val, item, span)?;
+                }
+                Ok(())
+            }
+            (Type::Tuple(types), Value::Tuple(fields)) if types.len() == fields.len() => {
+                for ((_, typ), (_, field)) in types.iter().zip(fields) {
+                    self.infer(env, &typ.val, field, span)?;
+                }
+                Ok(())
+            }
+            (Type::Generic(base, args), Value::Struct(name, _) | Value::Enum(name, ..))
+                if is_same_type(env, &base.val, name) =>
+            {
+                self.infer_type_args(env, name, args, value, span)
+            }
+            _ => {
+                let expected = self.substitute(expected);
+                let found = type_of(value, span);
+                if fits(env, &found, &expected) {
+                    return Ok(());
+                }
+                Err(Diagnostic::spanned(
+                    span,
+                    Level::Error,
+                    format!("Expected `{expected}`, found `{found}`"),
+                )
+                .with_child(span, Level::Error, format!("This is `{found}`")))
+            }
+        }
+    }
+
+    /// Infers the generic arguments of `Type[A, B]` from the fields or the variant of its value
+    fn infer_type_args(
+        &mut self,
+        env: &Env,
+        name: &str,
+        args: &[Spanned<Type>],
+        value: &Value,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
+        let Some(definition) = env.get_type(name) else {
+            return Ok(());
+        };
+        let instantiate = |params: &[GenericParam], typ: &Type| {
+            substitute(typ, &|name| {
+                let index = params.iter().position(|param| param.name.val == name)?;
+                args.get(index).map(|arg| arg.val.clone())
+            })
+        };
+        match (&definition.val, value) {
+            (Type::Struct(params, definitions), Value::Struct(_, fields)) => {
+                for (field, typ, ..) in definitions {
+                    if let Some(field) = fields.get(field) {
+                        self.infer(env, &instantiate(params, &typ.val), field, span)?;
+                    }
+                }
+            }
+            (Type::Enum(params, variants), Value::Enum(_, variant, Some(inner))) => {
+                let typ = variants
+                    .iter()
+                    .find(|(name, _)| name == variant)
+                    .and_then(|(_, typ)| typ.as_ref());
+                if let Some(typ) = typ {
+                    self.infer(env, &instantiate(params, &typ.val), inner, span)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn bind(
+        &mut self,
+        env: &Env,
+        param: &GenericParam,
+        typ: Spanned<Type>,
+    ) -> Result<(), Diagnostic> {
+        if let Some(bound) = &param.bound {
+            if !fits(env, &typ.val, &bound.val) {
+                return Err(Diagnostic::spanned(
+                    typ.span,
+                    Level::Error,
+                    format!(
+                        "`{typ}` does not satisfy the bound `{bound}` of `{}`",
+                        param.name
+                    ),
+                )
+                .with_child(
+                    typ.span,
+                    Level::Error,
+                    format!("`{}` is `{typ}` here", param.name),
+                )
+                .with_child(bound.span, Level::Note, "Bound declared here"));
+            }
+        }
+        self.types.insert(param.name.val.clone(), typ);
+        Ok(())
+    }
+
+    fn param(&self, path: &Path) -> Option<&GenericParam> {
+        let [name] = &path.parts[..] else {
+            return None;
+        };
+        self.params.iter().find(|param| &param.name.val == name)
+    }
+
+    /// Replaces the bound generic parameters in `typ` with their types
+    fn substitute(&self, typ: &Type) -> Type {
+        substitute(typ, &|name| self.types.get(name).map(|typ| typ.val.clone()))
+    }
+}
+
+/// Error for generic arguments that don't match the number of generic parameters
+pub fn arity_error(what: &str, expected: usize, args: &[Spanned<Type>]) -> Diagnostic {
+    let span = args[0].span.to(args[args.len() - 1].span);
+    if expected == 0 {
+        return Diagnostic::spanned(span, Level::Error, format!("{what} is not generic"))
+            .with_child(span, Level::Error, "Unexpected generic arguments");
+    }
+    let (plural, were) = if args.len() == 1 {
+        ("", "was")
+    } else {
+        ("s", "were")
+    };
+    Diagnostic::spanned(
+        span,
+        Level::Error,
+        format!(
+            "{what} takes {expected} generic argument{}, but {} {were} given",
+            if expected == 1 { "" } else { "s" },
+            args.len(),
+        ),
+    )
+    .with_child(
+        span,
+        Level::Error,
+        format!("{} generic argument{plural}", args.len()),
+    )
+}
+
+/// Replaces generic parameters in `typ` with the types of the function that is currently called
+fn resolve_type_args(env: &Env, typ: &Spanned<Type>) -> Spanned<Type> {
+    let val = substitute(&typ.val, &|name| {
+        env.get_type_arg(name).map(|arg| arg.val.clone())
+    });
+    Spanned::new(val, typ.span)
+}
+
+/// Replaces the names in `typ` for which `lookup` returns a type
+fn substitute(typ: &Type, lookup: &impl Fn(&str) -> Option<Type>) -> Type {
+    let spanned = |typ: &Spanned<Type>| Spanned::new(substitute(&typ.val, lookup), typ.span);
+    match typ {
+        Type::Path(path) => match &path.parts[..] {
+            [name] => lookup(name).unwrap_or_else(|| typ.clone()),
+            _ => typ.clone(),
+        },
+        Type::Generic(base, args) => {
+            Type::Generic(Box::new(spanned(base)), args.iter().map(spanned).collect())
+        }
+        Type::Function {
+            args,
+            named_args,
+            return_type,
+        } => Type::Function {
+            args: args.iter().map(spanned).collect(),
+            named_args: named_args
+                .iter()
+                .map(|(name, typ, default)| (name.clone(), spanned(typ), default.clone()))
+                .collect(),
+            return_type: Box::new(spanned(return_type)),
+        },
+        Type::Array(size, item) => Type::Array(size.clone(), Box::new(spanned(item))),
+        Type::Tuple(fields) => Type::Tuple(
+            fields
+                .iter()
+                .map(|(name, typ)| (name.clone(), spanned(typ)))
+                .collect(),
+        ),
+        Type::Union(variants) => Type::Union(variants.iter().map(spanned).collect()),
+        Type::Unit
+        | Type::Unkown
+        | Type::Never
+        | Type::Literal(_)
+        | Type::Struct(..)
+        | Type::Enum(..) => typ.clone(),
+    }
+}
+
+/// Type of a value, as far as it is known at runtime.
+/// The types inside of it are spanned with the span of the value.
+pub fn type_of(value: &Value, span: Span) -> Type {
+    match value {
+        Value::Unit => Type::Unit,
+        Value::Bool(_) => named("bool"),
+        Value::Int(_) => named("int"),
+        Value::SizedInt(_, typ) => named(&typ.to_string()),
+        Value::Float(_) => named("float"),
+        Value::Char(_) => named("char"),
+        Value::String(_) => named("String"),
+        Value::Array(items) => {
+            let item = items
+                .first()
+                .map(|item| type_of(item, span))
+                .unwrap_or(Type::Unkown);
+            Type::Array(ArraySize::Unknown, Box::new(Spanned::new(item, span)))
+        }
+        Value::Tuple(fields) => Type::Tuple(
+            fields
+                .iter()
+                .map(|(name, field)| (name.clone(), Spanned::new(type_of(field, span), span)))
+                .collect(),
+        ),
+        Value::Struct(name, _) | Value::Enum(name, ..) => named(name),
+        Value::Function(func, _) => Type::Function {
+            args: func
+                .params
+                .iter()
+                .map(|param| param.val.1.clone())
+                .collect(),
+            named_args: vec![],
+            return_type: Box::new(func.return_type.clone()),
+        },
+        Value::NativeFunction(_) | Value::Module(..) => Type::Unkown,
+    }
+}
+
+/// Whether `typ` is the path of the type `name`, which can also be written as `Self`
+fn is_same_type(env: &Env, typ: &Type, name: &str) -> bool {
+    matches!(typ, Type::Path(path) if env.resolve_self(&path.to_string()) == name)
+}
+
+fn named(name: &str) -> Type {
+    Type::Path(Path::new(vec![name.to_string()]))
+}
+
+/// Whether a value of type `found` can be used where a value of type `expected` is expected.
+/// Types which can't be told apart at runtime always fit.
+fn fits(env: &Env, found: &Type, expected: &Type) -> bool {
+    let (found, expected) = (resolve_alias(env, found), resolve_alias(env, expected));
+    match (&found, &expected) {
+        (Type::Unkown, _) | (_, Type::Unkown) => true,
+        (Type::Union(variants), _) => variants
+            .iter()
+            .all(|variant| fits(env, &variant.val, &expected)),
+        (_, Type::Union(variants)) => variants
+            .iter()
+            .any(|variant| fits(env, &found, &variant.val)),
+        (Type::Path(found), Type::Path(expected)) => {
+            runtime_name(env.resolve_self(&found.to_string()))
+                == runtime_name(env.resolve_self(&expected.to_string()))
+        }
+        (Type::Generic(found, found_args), Type::Generic(expected, expected_args)) => {
+            fits(env, &found.val, &expected.val)
+                && found_args.len() == expected_args.len()
+                && found_args
+                    .iter()
+                    .zip(expected_args)
+                    .all(|(found, expected)| fits(env, &found.val, &expected.val))
+        }
+        // Values don't know the generic arguments of their type
+        (Type::Generic(base, _), _) => fits(env, &base.val, &expected),
+        (_, Type::Generic(base, _)) => fits(env, &found, &base.val),
+        (Type::Array(_, found), Type::Array(_, expected)) => fits(env, &found.val, &expected.val),
+        (Type::Tuple(found), Type::Tuple(expected)) => {
+            found.len() == expected.len()
+                && found
+                    .iter()
+                    .zip(expected)
+                    .all(|((_, found), (_, expected))| fits(env, &found.val, &expected.val))
+        }
+        (Type::Function { .. }, Type::Function { .. }) => true,
+        (Type::Literal(found), Type::Literal(expected)) => found == expected,
+        (Type::Literal(lit), _) => {
+            let name = match lit {
+                Literal::Bool(_) => "bool",
+                Literal::Int(..) => "int",
+                Literal::Float(..) => "float",
+                Literal::Char(_) => "char",
+                Literal::String(_) => "String",
+            };
+            fits(env, &named(name), &expected)
+        }
+        (_, Type::Struct(..) | Type::Enum(..)) => true,
+        (Type::Unit, Type::Unit) => true,
+        _ => false,
+    }
+}
+
+/// Replaces the name of a type alias or of a generic parameter with its type
+fn resolve_alias(env: &Env, typ: &Type) -> Type {
+    let Type::Path(Path { parts }) = typ else {
+        return typ.clone();
+    };
+    let [name] = &parts[..] else {
+        return typ.clone();
+    };
+    if let Some(arg) = env.get_type_arg(name) {
+        return arg.val.clone();
+    }
+    match env.get_type(name) {
+        Some(Spanned {
+            val: Type::Struct(..) | Type::Enum(..),
+            ..
+        })
+        | None => typ.clone(),
+        Some(alias) => alias.val.clone(),
+    }
+}
+
+/// Integers and floats of every size are the same at runtime
+fn runtime_name(name: &str) -> &str {
+    let sized = |prefix: char| {
+        name.strip_prefix(prefix)
+            .is_some_and(|bits| !bits.is_empty() && bits.bytes().all(|byte| byte.is_ascii_digit()))
+    };
+    if sized('i') || sized('u') || name == "isize" || name == "usize" {
+        "int"
+    } else if sized('f') {
+        "float"
+    } else {
+        name
+    }
+}
@@ -1,24 +1,33 @@
 #![feature(let_chains)]
 
-use std::sync::mpsc::Sender;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+};
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{confusable_detection::skeleton, MixedScript};
 
 mod ast;
 mod compile;
 mod error;
+pub mod highlight;
 mod interpret;
 mod parse;
+mod resolve;
 mod span;
 pub mod std_lib;
+pub mod testing;
 mod tokenize;
 
 pub use error::AriadneEmitter;
 pub use interpret::Env;
 pub use tokenize::tokenizer;
 
-use error::{Diagnostic, Level, SourceId};
+use error::{Diagnostic, Level, SourceId, SourceMap};
 use interpret::{ControlFlow, Interpret, Value};
-use span::Span;
-use tokenize::Token;
+use span::{Span, Spanned};
+use tokenize::{Token, TokenizeError};
 
 pub fn interpret(env: &mut Env, ast: impl Interpret) -> Option<Value> {
     // let env_state: String = env
@@ -40,51 +49,219 @@ pub fn interpret(env: &mut Env, ast: impl Interpret) -> Option<Value> {
     match ast.eval(env) {
         ControlFlow::Exit => None,
         ControlFlow::None(inner) => Some(inner),
-        ControlFlow::Break(inner) => Some(inner),
+        ControlFlow::Break(_, inner) => Some(inner),
+        ControlFlow::Continue(_) => Some(Value::Unit),
         ControlFlow::Return(inner) => Some(inner),
     }
 }
 
+/// Parses the file and the files of its modules and resolves the names in them.
+/// If there were any errors, the partial module is returned as the error,
+/// if parsing got far enough to produce one.
 pub fn compile_module(
     emitter: Sender<Diagnostic>,
-    src: &str,
+    source_map: &SourceMap,
     src_id: SourceId,
-) -> Option<ast::Module> {
-    let tokens: Vec<(Token, Span)> = tokenize(emitter.clone(), src, src_id);
+) -> Result<ast::Module, Option<ast::Module>> {
+    let module = parse_module(&emitter, source_map, src_id, &mut vec![])?;
+    let diagnostics = resolve::resolve_module(&module);
+    if !diagnostics.is_empty() {
+        for diagnostic in diagnostics {
+            emitter.send(diagnostic).unwrap();
+        }
+        return Err(Some(module));
+    }
 
-    let (module, diagnostics) = parse::parse_file(&tokens, src, src_id);
+    // TODO Name resolution
+    // TODO Typechecking
+    // TODO Const evaluation
+    // TODO Generate intermediate representation
+
+    Ok(module)
+}
+
+/// Parses a file together with the files of its modules and imports.
+/// `loading` holds the files which are currently being loaded, to detect cyclic imports.
+/// See [`compile_module`] for the partial module returned on errors.
+fn parse_module(
+    emitter: &Sender<Diagnostic>,
+    source_map: &SourceMap,
+    src_id: SourceId,
+    loading: &mut Vec<PathBuf>,
+) -> Result<ast::Module, Option<ast::Module>> {
+    let src = source_map
+        .text(src_id)
+        .expect("Internal Error: Source file does not exist");
+    let tokens: Vec<(Token, Span)> = tokenize(emitter.clone(), &src, src_id);
+
+    let (module, diagnostics) = parse::parse_file(&tokens, &src, src_id);
+    let has_errors = diagnostics.iter().any(|diag| diag.level == Level::Error);
     for diagnostic in diagnostics {
         emitter.send(diagnostic).unwrap();
     }
     Diagnostic::new(Level::Debug, "Finished parsing")
         .with_child(vec![], Level::Debug, format!("{module:#?}\n"))
-        .emit(emitter);
+        .emit(emitter.clone());
 
-    // TODO Name resolution
-    // TODO Typechecking
-    // TODO Const evaluation
-    // TODO Generate intermediate representation
+    let Some(mut module) = module else {
+        return Err(None);
+    };
+    let path = PathBuf::from(source_map.name(src_id).unwrap_or_default());
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    loading.push(canonical_path(&path));
+    let loaded = load_modules(emitter, source_map, &dir, &mut module, loading);
+    loading.pop();
+
+    // The module only contains placeholders where the errors are
+    if loaded && !has_errors {
+        Ok(module)
+    } else {
+        Err(Some(module))
+    }
+}
+
+/// Replaces `module name;` declarations and constants like `const lib = import("lib.rym")`
+/// with the modules in these files, which are relative to `dir`.
+/// Modules with errors are still inserted, but the result is `false` then.
+fn load_modules(
+    emitter: &Sender<Diagnostic>,
+    source_map: &SourceMap,
+    dir: &Path,
+    module: &mut ast::Module,
+    loading: &mut Vec<PathBuf>,
+) -> bool {
+    let mut success = true;
+    for sub_module in &mut module.sub_modules {
+        let Some(file) = sub_module.file.clone() else {
+            success &= load_modules(emitter, source_map, dir, sub_module, loading);
+            continue;
+        };
+        let loaded = load_file(
+            emitter,
+            source_map,
+            &dir.join(&file.val),
+            file.span,
+            loading,
+        );
+        success &= loaded.is_ok();
+        if let Ok(loaded) | Err(Some(loaded)) = loaded {
+            *sub_module = ast::Module {
+                name: sub_module.name.clone(),
+                visibility: sub_module.visibility,
+                doc: sub_module.doc.clone(),
+                file: Some(file),
+                ..loaded
+            }
+        }
+    }
 
-    module
+    for (name, _, expr, _, _) in &mut module.constants {
+        let Some(file) = import_path(expr) else {
+            continue;
+        };
+        let loaded = load_file(emitter, source_map, &dir.join(&file), expr.span, loading);
+        success &= loaded.is_ok();
+        if let Ok(loaded) | Err(Some(loaded)) = loaded {
+            expr.val = ast::Expr::Module(Box::new(ast::Module {
+                name: name.clone(),
+                file: Some(Spanned::new(file, expr.span)),
+                ..loaded
+            }))
+        }
+    }
+
+    success
+}
+
+/// The file of `import("file.rym")`, the standard library imported with `import("std")` is built in
+fn import_path(expr: &Spanned<ast::Expr>) -> Option<String> {
+    let ast::Expr::Call(callee, args, named_args) = &expr.val else {
+        return None;
+    };
+    match (&callee.val, args.as_slice()) {
+        (
+            ast::Expr::Ident(name),
+            [Spanned {
+                val: ast::Expr::Literal(ast::Literal::String(file)),
+                ..
+            }],
+        ) if name == "import" && named_args.is_empty() && file != "std" => Some(file.clone()),
+        _ => None,
+    }
+}
+
+fn load_file(
+    emitter: &Sender<Diagnostic>,
+    source_map: &SourceMap,
+    path: &Path,
+    span: Span,
+    loading: &mut Vec<PathBuf>,
+) -> Result<ast::Module, Option<ast::Module>> {
+    if loading.contains(&canonical_path(path)) {
+        Diagnostic::spanned(
+            span,
+            Level::Error,
+            format!("Module `{}` imports itself", path.display()),
+        )
+        .with_child(span, Level::Error, "Cyclic import")
+        .emit(emitter.clone());
+        return Err(None);
+    }
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(err) => {
+            Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!("Unable to read module file `{}`", path.display()),
+            )
+            .with_child(span, Level::Error, err.to_string())
+            .emit(emitter.clone());
+            return Err(None);
+        }
+    };
+    let src_id = source_map.add(path.to_string_lossy(), src);
+    parse_module(emitter, source_map, src_id, loading)
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
 }
 
 // TODO take a module (for name lookup and so on) as input
-pub fn compile_stmt(emitter: Sender<Diagnostic>, src: &str, src_id: SourceId) -> Option<ast::Stmt> {
+pub fn compile_stmt(
+    emitter: Sender<Diagnostic>,
+    src: &str,
+    src_id: SourceId,
+) -> Option<Spanned<ast::Stmt>> {
     let tokens: Vec<(Token, Span)> = tokenize(emitter.clone(), src, src_id);
 
     let (expr, diagnostics) = parse::parse_stmt(&tokens, src, src_id);
+    let has_errors = diagnostics.iter().any(|diag| diag.level == Level::Error);
     for diagnostic in diagnostics {
         emitter.send(diagnostic).unwrap();
     }
     Diagnostic::new(Level::Debug, "Finished parsing")
         .with_child(vec![], Level::Debug, format!("{expr:#?}\n"))
-        .emit(emitter);
+        .emit(emitter.clone());
+    if has_errors {
+        return None;
+    }
+
+    let expr = expr?;
+    let diagnostics = resolve::resolve_stmt(&expr);
+    if !diagnostics.is_empty() {
+        for diagnostic in diagnostics {
+            emitter.send(diagnostic).unwrap();
+        }
+        return None;
+    }
 
-    expr
+    Some(expr)
 }
 
 fn tokenize(emitter: Sender<Diagnostic>, src: &str, src_id: SourceId) -> Vec<(Token, Span)> {
-    let results: Vec<(Option<Token>, Span)> = tokenizer(src)
+    let results: Vec<(Result<Token, TokenizeError>, Span)> = tokenizer(src)
         .map(|(token, span)| (token, span.with_id(src_id)))
         .collect();
 
@@ -95,8 +272,8 @@ fn tokenize(emitter: Sender<Diagnostic>, src: &str, src_id: SourceId) -> Vec<(To
             .iter()
             .fold(String::new(), |mut accum, (result, span)| {
                 match result {
-                    Some(token) => write!(accum, "{token:?}"),
-                    None => write!(accum, "Error"),
+                    Ok(token) => write!(accum, "{token:?}"),
+                    Err(_) => write!(accum, "Error"),
                 }
                 .unwrap();
                 // TODO Add option to display spans as well
@@ -108,17 +285,150 @@ fn tokenize(emitter: Sender<Diagnostic>, src: &str, src_id: SourceId) -> Vec<(To
             .emit(emitter.clone());
     }
 
-    // Report and ignore invalid characters
-    results
+    // Report and ignore invalid tokens
+    let tokens: Vec<(Token, Span)> = results
         .into_iter()
-        .flat_map(|(maybe_token, span)| match maybe_token {
-            Some(Token::DocComment | Token::Comment | Token::VSpace | Token::HSpace) => None,
-            Some(token) => Some((token, span)),
-            None => {
-                let message = format!("Invalid character `{}`", span.src(src));
-                Diagnostic::spanned(span, Level::Error, message).emit(emitter.clone());
+        .flat_map(|(result, span)| match result {
+            Ok(Token::Comment | Token::VSpace | Token::HSpace) => None,
+            Ok(token) => Some((token, span)),
+            Err(error) => {
+                error.to_diagnostic(span, src).emit(emitter.clone());
                 None
             }
         })
-        .collect()
+        .collect();
+
+    check_identifiers(&emitter, src, &tokens);
+    filter_doc_comments(emitter, src, tokens)
+}
+
+/// Warns about identifiers, which could easily be mistaken for other identifiers
+fn check_identifiers(emitter: &Sender<Diagnostic>, src: &str, tokens: &[(Token, Span)]) {
+    let idents: Vec<(String, Span)> = tokens
+        .iter()
+        .filter(|(token, _)| *token == Token::Ident)
+        .map(|(_, span)| (span.src(src).nfc().collect(), *span))
+        .collect();
+    // Only ASCII identifiers can't be confused with each other
+    if idents.iter().all(|(ident, _)| ident.is_ascii()) {
+        return;
+    }
+
+    let mut skeletons: HashMap<String, (&str, Span)> = HashMap::new();
+    let mut reported = HashSet::new();
+    for (ident, span) in &idents {
+        if !ident.as_str().is_single_script() && reported.insert(ident) {
+            Diagnostic::spanned(
+                *span,
+                Level::Warning,
+                format!("Identifier `{ident}` mixes characters from different scripts"),
+            )
+            .with_child(
+                *span,
+                Level::Warning,
+                "Contains characters from different scripts",
+            )
+            .with_child(
+                vec![],
+                Level::Help,
+                "Characters from different scripts can look alike, \
+                like the latin `a` and the cyrillic `а`",
+            )
+            .emit(emitter.clone());
+        }
+
+        match skeletons.get(&skeleton(ident).collect::<String>()) {
+            Some((other, other_span)) if other != ident && reported.insert(ident) => {
+                Diagnostic::spanned(
+                    *span,
+                    Level::Warning,
+                    format!("Identifier `{ident}` can be confused with `{other}`"),
+                )
+                .with_child(*span, Level::Warning, format!("This looks like `{other}`"))
+                .with_child(*other_span, Level::Note, format!("`{other}` is used here"))
+                .emit(emitter.clone());
+            }
+            Some(_) => {}
+            None => {
+                skeletons.insert(skeleton(ident).collect(), (ident, *span));
+            }
+        }
+    }
+}
+
+/// Only keeps doc comments in front of definitions, modules, impl blocks, tests and struct fields,
+/// all other doc comments get reported and ignored
+fn filter_doc_comments(
+    emitter: Sender<Diagnostic>,
+    src: &str,
+    tokens: Vec<(Token, Span)>,
+) -> Vec<(Token, Span)> {
+    let report = |doc_comments: &[(Token, Span)]| {
+        let (Some((_, first)), Some((_, last))) = (doc_comments.first(), doc_comments.last())
+        else {
+            return;
+        };
+        let span = Span::new(first.start, last.end).with_id(first.id);
+        let message = "Doc comment is not attached to a definition";
+        Diagnostic::spanned(span, Level::Warning, message)
+            .with_child(span, Level::Warning, "This doc comment documents nothing")
+            .with_child(
+                vec![],
+                Level::Help,
+                "Doc comments have to be in front of `const`, `let`, `fn`, `type`, `module`, \
+                `impl`, `test` or struct fields, use `//` for regular comments",
+            )
+            .emit(emitter.clone());
+    };
+
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut doc_comments = vec![];
+    let mut in_visibility = false;
+    // Braces of struct types, in which the fields are documented
+    let mut after_struct = false;
+    let mut struct_depth = 0;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some((token, span)) = tokens.next() {
+        let after_pub = std::mem::take(&mut in_visibility);
+        let next = tokens.peek().map(|(token, _)| *token);
+        match token {
+            Token::DocComment => doc_comments.push((token, span)),
+            Token::Const | Token::Let | Token::Fn | Token::Type | Token::Module | Token::Impl => {
+                result.append(&mut doc_comments);
+                result.push((token, span));
+            }
+            // `test "name" { .. }` and `field: type` inside of a struct
+            Token::Ident
+                if (span.src(src) == "test" && matches!(next, Some(Token::String)))
+                    || (struct_depth > 0 && next == Some(Token::Colon)) =>
+            {
+                result.append(&mut doc_comments);
+                result.push((token, span));
+            }
+            // Doc comments in front of a visibility like `pub(package)` are moved behind it,
+            // where the parser expects them
+            Token::Pub => {
+                in_visibility = true;
+                result.push((token, span));
+            }
+            Token::ParenOpen | Token::Ident | Token::ParenClose if after_pub => {
+                in_visibility = token != Token::ParenClose;
+                result.push((token, span));
+            }
+            _ => {
+                match token {
+                    Token::Struct => after_struct = true,
+                    Token::BraceOpen if std::mem::take(&mut after_struct) => struct_depth += 1,
+                    Token::BraceClose if struct_depth > 0 => struct_depth -= 1,
+                    _ => {}
+                }
+                report(&doc_comments);
+                doc_comments.clear();
+                result.push((token, span));
+            }
+        }
+    }
+    report(&doc_comments);
+
+    result
 }
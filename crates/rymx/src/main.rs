@@ -1,7 +1,13 @@
 use clap::{arg, command, Command};
 use rustyline::{error::ReadlineError, Editor};
-use rymx::{compile_module, compile_stmt, interpret, AriadneEmitter, Env};
-use std::{fs::read_to_string, path::PathBuf};
+use rymx::{
+    compile_module, compile_stmt, highlight, interpret, testing::run_tests, AriadneEmitter, Env,
+};
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 #[derive(Debug)]
 struct Arguments {}
@@ -14,6 +20,22 @@ fn main() -> anyhow::Result<()> {
             Command::new("run")
                 .about("Execute a file")
                 .arg(arg!(<file> "File to execute")),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Run the tests of a file or of all files in a directory")
+                .arg(arg!([path] "File or directory with tests").default_value("."))
+                .arg(arg!(-f --filter <NAME> "Only run tests whose name contains NAME")),
+        )
+        .subcommand(
+            Command::new("highlight")
+                .about("Print a file with syntax highlighting")
+                .arg(arg!(<file> "File to highlight"))
+                .arg(
+                    arg!(-f --format <FORMAT> "Output format")
+                        .value_parser(["ansi", "html"])
+                        .default_value("ansi"),
+                ),
         );
 
     let help_str = command.render_help();
@@ -28,6 +50,20 @@ fn main() -> anyhow::Result<()> {
             write_flags,
             sub_matches.get_one::<String>("file").unwrap().into(),
         )?,
+        Some(("test", sub_matches)) => cmd_test(
+            sub_matches.get_one::<String>("path").unwrap().into(),
+            sub_matches
+                .get_one::<String>("filter")
+                .cloned()
+                .unwrap_or_default(),
+        )?,
+        Some(("highlight", sub_matches)) => cmd_highlight(
+            sub_matches.get_one::<String>("file").unwrap().into(),
+            match sub_matches.get_one::<String>("format").unwrap().as_str() {
+                "html" => highlight::Format::Html,
+                _ => highlight::Format::Ansi,
+            },
+        )?,
         _ => print!("{}", help_str.ansi()),
     }
 
@@ -51,7 +87,7 @@ fn cmd_repl(_write_flags: Vec<String>) -> anyhow::Result<()> {
     //     Box::new(File::create(PathBuf::from("repl.debug"))?)
     // };
 
-    let (sender, mut emitter) = AriadneEmitter::new(std::io::stderr());
+    let (sender, emitter) = AriadneEmitter::new(std::io::stderr());
     let mut env = Env::new(sender.clone()).with_constants(rymx::std_lib::CONSTANTS);
     let src_id = emitter.source_map.add("repl", "");
     loop {
@@ -104,11 +140,12 @@ fn cmd_run(_write_flags: Vec<String>, path: PathBuf) -> anyhow::Result<()> {
     //     Box::new(File::create(path.with_extension("debug"))?)
     // };
 
-    let (sender, mut emitter) = AriadneEmitter::new(std::io::stderr());
+    let (sender, emitter) = AriadneEmitter::new(std::io::stderr());
     let src_id = emitter.source_map.add(path.to_string_lossy(), &src);
+    let source_map = emitter.source_map.clone();
 
     std::thread::spawn(move || {
-        let module = compile_module(sender.clone(), &src, src_id)?;
+        let module = compile_module(sender.clone(), &source_map, src_id).ok()?;
         let mut env = Env::new(sender).with_constants(rymx::std_lib::CONSTANTS);
         interpret(&mut env, module);
         Some(())
@@ -117,3 +154,105 @@ fn cmd_run(_write_flags: Vec<String>, path: PathBuf) -> anyhow::Result<()> {
     emitter.emit_all_blocking();
     Ok(())
 }
+
+fn cmd_test(path: PathBuf, filter: String) -> anyhow::Result<()> {
+    let (sender, emitter) = AriadneEmitter::new(std::io::stderr());
+    let emitter = emitter.without_debug();
+    let mut files = vec![];
+    for file in rym_files(&path)? {
+        let src = read_to_string(&file)?;
+        files.push((
+            file.display().to_string(),
+            emitter.source_map.add(file.to_string_lossy(), &src),
+        ));
+    }
+    let source_map = emitter.source_map.clone();
+
+    let runner = std::thread::spawn(move || {
+        let start = Instant::now();
+        let (mut passed, mut failed, mut not_compiled) = (0, 0, 0);
+        for (name, src_id) in files {
+            let Ok(module) = compile_module(sender.clone(), &source_map, src_id) else {
+                println!("{name} failed to compile");
+                not_compiled += 1;
+                continue;
+            };
+            let results = run_tests(module, &filter, || {
+                Env::new(sender.clone()).with_constants(rymx::std_lib::CONSTANTS)
+            });
+            if results.is_empty() {
+                continue;
+            }
+
+            println!("running {} tests in {name}", results.len());
+            for result in results {
+                let status = if result.passed {
+                    passed += 1;
+                    "ok"
+                } else {
+                    failed += 1;
+                    "FAILED"
+                };
+                let module = match result.module.is_empty() {
+                    true => String::new(),
+                    false => format!(" in {}", result.module.join(".")),
+                };
+                println!(
+                    "test \"{}\"{module} ... {status} ({:.2?})",
+                    result.name, result.duration
+                );
+            }
+            println!();
+        }
+
+        let status = if failed + not_compiled == 0 {
+            "ok"
+        } else {
+            "FAILED"
+        };
+        print!("test result: {status}. {passed} passed; {failed} failed; ");
+        if not_compiled > 0 {
+            print!("{not_compiled} files failed to compile; ");
+        }
+        println!("finished in {:.2?}", start.elapsed());
+        (failed, not_compiled)
+    });
+
+    emitter.emit_all_blocking();
+    let Ok((failed, not_compiled)) = runner.join() else {
+        anyhow::bail!("The test runner crashed");
+    };
+    // The summary was already printed
+    if failed + not_compiled > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `path` itself or all `.rym` files inside of the directory `path`, sorted by their path
+fn rym_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        let is_hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if path.is_dir() && !is_hidden {
+            files.extend(rym_files(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == "rym") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn cmd_highlight(path: PathBuf, format: highlight::Format) -> anyhow::Result<()> {
+    let src = read_to_string(&path)?;
+    print!("{}", highlight::highlight(&src, format));
+    Ok(())
+}
@@ -2,7 +2,7 @@ use self::error::{ParseError, Pattern};
 use crate::{
     ast::{Module, Stmt},
     error::{Diagnostic, Level, SourceId},
-    span::Span,
+    span::{Span, Spanned},
     tokenize::Token,
 };
 use chumsky::{input::Input, prelude::*};
@@ -11,19 +11,22 @@ use itertools::Itertools;
 mod common;
 mod error;
 mod file;
+mod pattern;
 mod stmt;
 mod r#type;
 
 pub(self) use file::file_parser;
+pub(self) use pattern::pattern_parser;
 pub(self) use r#type::type_parser;
-pub(self) use stmt::stmt_parser;
+pub(self) use stmt::{expr_parser, stmt_parser};
 
 pub fn parse_file<'a>(
     tokens: &'a [(Token, Span)],
     src: &'a str,
     src_id: SourceId,
 ) -> (Option<Module>, Vec<Diagnostic>) {
-    let parse_result = file_parser(src).parse(tokens.spanned(Span::new(src.len(), src.len())));
+    let parse_result =
+        file_parser(src).parse(tokens.spanned(Span::new(src.len(), src.len()).with_id(src_id)));
 
     map_parse_result(parse_result, src_id)
 }
@@ -32,8 +35,9 @@ pub fn parse_stmt<'a>(
     tokens: &'a [(Token, Span)],
     src: &'a str,
     src_id: SourceId,
-) -> (Option<Stmt>, Vec<Diagnostic>) {
-    let parse_result = stmt_parser(src).parse(tokens.spanned(Span::new(src.len(), src.len())));
+) -> (Option<Spanned<Stmt>>, Vec<Diagnostic>) {
+    let parse_result =
+        stmt_parser(src).parse(tokens.spanned(Span::new(src.len(), src.len()).with_id(src_id)));
 
     map_parse_result(parse_result, src_id)
 }
@@ -132,7 +136,10 @@ fn patterns_to_string(patterns: Vec<Pattern>) -> String {
         &[
             Pattern::Token(Token::Int),
             Pattern::Token(Token::Float),
+            Pattern::Token(Token::Char),
             Pattern::Token(Token::String),
+            Pattern::Token(Token::RawString),
+            Pattern::Token(Token::MultiLineString),
         ],
         &Pattern::Label("literal"),
     );
@@ -155,17 +162,28 @@ fn patterns_to_string(patterns: Vec<Pattern>) -> String {
         &[
             Pattern::Token(Token::Slash),
             Pattern::Token(Token::Star),
+            Pattern::Token(Token::Mod),
             Pattern::Token(Token::Plus),
             Pattern::Token(Token::Minus),
+            Pattern::Token(Token::PlusPlus),
         ],
         &Pattern::Label("an arithmetic operator"),
     );
 
+    replace_subset(
+        &mut patterns,
+        &[Pattern::Token(Token::And), Pattern::Token(Token::Or)],
+        &Pattern::Label("a logical operator"),
+    );
+
     replace_subset(
         &mut patterns,
         &[
             Pattern::Token(Token::Assign),
-            // TODO Add others
+            Pattern::Token(Token::PlusAssign),
+            Pattern::Token(Token::MinusAssign),
+            Pattern::Token(Token::StarAssign),
+            Pattern::Token(Token::SlashAssign),
         ],
         &Pattern::Label("an assignment operator"),
     );
@@ -175,6 +193,7 @@ fn patterns_to_string(patterns: Vec<Pattern>) -> String {
         &[
             Pattern::Label("a comparison operator"),
             Pattern::Label("an arithmetic operator"),
+            Pattern::Label("a logical operator"),
             Pattern::Label("an assignment operator"),
         ],
         &Pattern::Label("an operator"),
@@ -189,11 +208,19 @@ fn patterns_to_string(patterns: Vec<Pattern>) -> String {
             Pattern::Token(Token::BraceOpen),
             Pattern::Token(Token::BracketOpen),
             Pattern::Token(Token::Break),
+            Pattern::Token(Token::Continue),
             Pattern::Token(Token::Return),
         ],
         &Pattern::Label("expression"),
     );
 
+    // Only reached after a prefix operator
+    replace_element(
+        &mut patterns,
+        &Pattern::Label("atom"),
+        &Pattern::Label("expression"),
+    );
+
     replace_subset(
         &mut patterns,
         &[
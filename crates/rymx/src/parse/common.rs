@@ -1,33 +1,122 @@
 use super::{error::ParseError, type_parser};
-use crate::{ast::*, tokenize::Token, Span};
+use crate::{
+    ast::*,
+    error::{Diagnostic, Level},
+    span::Spanned,
+    tokenize::Token,
+    Span,
+};
 use chumsky::{
     input::{MapExtra, SpannedInput},
     prelude::*,
 };
+use std::collections::BTreeMap;
+use unicode_normalization::UnicodeNormalization;
 
 pub(super) type TokenStream<'tokens> = SpannedInput<Token, Span, &'tokens [(Token, Span)]>;
 pub(super) type Extra<'src> = extra::Full<ParseError, (), &'src str>;
 // pub(super) type MyParser<'src, 'token, O> = Parser<'src, TokenStream<'token>, O, Extra<'src>>;
 
+pub(super) type Parameters = (
+    Vec<Spanned<(String, Spanned<Type>)>>,
+    BTreeMap<String, (Spanned<Type>, Spanned<Expr>)>,
+);
+
 pub(super) fn parameters_parser<'src>(
-) -> impl Parser<'src, TokenStream<'src>, Vec<(String, Type)>, Extra<'src>> + Clone {
-    // parameter ::= ident (":" type)?
+    expr: impl Parser<'src, TokenStream<'src>, Spanned<Expr>, Extra<'src>> + Clone + 'src,
+) -> impl Parser<'src, TokenStream<'src>, Parameters, Extra<'src>> + Clone {
+    // parameter ::= ident (":" type)? ("=" expr)?
     let parameter = ident_parser()
+        .map_with(spanned)
         .then(just(Token::Colon).ignore_then(type_parser()).or_not())
-        .map(|(name, maybe_typ)| (name.to_string(), maybe_typ.unwrap_or(Type::Unkown)))
+        .then(just(Token::Assign).ignore_then(expr).or_not())
+        .map(|((name, maybe_typ), default)| {
+            let typ = maybe_typ.unwrap_or(Spanned::new(Type::Unkown, name.span));
+            (name, typ, default)
+        })
         .labelled("parameter");
 
     // parameters ::= (parameter ("," parameter)*)?
     parameter
         .separated_by(just(Token::Comma))
         .allow_trailing()
-        .collect()
+        .collect::<Vec<_>>()
+        .validate(|params, _, emitter| {
+            let mut positional = vec![];
+            let mut named = BTreeMap::new();
+            let mut seen: Vec<Spanned<String>> = vec![];
+            for (name, typ, default) in params {
+                if let Some(first) = seen.iter().find(|prev| prev.val == name.val) {
+                    emitter.emit(specified_twice("Parameter", &name, first.span));
+                    continue;
+                }
+                seen.push(name.clone());
+                match default {
+                    // Parameters with a default value can only be passed by name
+                    Some(default) => {
+                        named.insert(name.val, (typ, default));
+                    }
+                    None => {
+                        let span = name.span.to(typ.span);
+                        positional.push(Spanned::new((name.val, typ), span));
+                    }
+                }
+            }
+            (positional, named)
+        })
+}
+
+/// Takes the type parser, because it is also used inside of it
+pub(super) fn generic_params_parser<'src>(
+    type_: impl Parser<'src, TokenStream<'src>, Spanned<Type>, Extra<'src>> + Clone + 'src,
+) -> impl Parser<'src, TokenStream<'src>, Vec<GenericParam>, Extra<'src>> + Clone {
+    // generic_param ::= ident (":" type)?
+    let param = ident_parser()
+        .map_with(spanned)
+        .then(just(Token::Colon).ignore_then(type_).or_not())
+        .map(|(name, bound)| GenericParam { name, bound })
+        .labelled("generic parameter");
+
+    // generic_params ::= "[" generic_param ("," generic_param)* ","? "]"
+    param
+        .separated_by(just(Token::Comma))
+        .allow_trailing()
+        .at_least(1)
+        .collect::<Vec<GenericParam>>()
+        .delimited_by(just(Token::BracketOpen), just(Token::BracketClose))
+        .validate(|params, _, emitter| {
+            for (index, param) in params.iter().enumerate() {
+                if let Some(first) = params[..index]
+                    .iter()
+                    .find(|prev| prev.name.val == param.name.val)
+                {
+                    emitter.emit(specified_twice(
+                        "Generic parameter",
+                        &param.name,
+                        first.name.span,
+                    ));
+                }
+            }
+            params
+        })
+}
+
+/// Error for a name that is already used by a previous parameter, field or argument
+pub(super) fn specified_twice(what: &str, name: &Spanned<String>, first: Span) -> ParseError {
+    ParseError::diagnostic(
+        name.span,
+        Diagnostic::new(
+            Level::Error,
+            format!("{what} `{}` is specified more than once", name.val),
+        )
+        .with_child(name.span, Level::Error, "Specified again here")
+        .with_child(first, Level::Note, "First specified here"),
+    )
 }
 
 pub fn path_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Path, Extra<'src>> + Clone {
     // path ::= ident ("." ident)*
     ident_parser()
-        .map(String::from)
         .separated_by(just(Token::Dot))
         .at_least(1)
         .collect::<Vec<String>>()
@@ -36,51 +125,555 @@ pub fn path_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Path, Extra<'
 
 pub fn literal_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Literal, Extra<'src>> + Clone
 {
+    literals(false)
+}
+
+/// Literals of expressions, which might be negated afterwards.
+/// Integers can be one larger than the maximum of their type, like the `128` in `-128i8`,
+/// unary expressions check that these are negated.
+pub(super) fn negatable_literal_parser<'src>(
+) -> impl Parser<'src, TokenStream<'src>, Literal, Extra<'src>> + Clone {
+    literals(true)
+}
+
+fn literals<'src>(
+    negatable: bool,
+) -> impl Parser<'src, TokenStream<'src>, Literal, Extra<'src>> + Clone {
     let integer = just(Token::Int)
-        .map_with(|_, extra| {
-            Literal::Int(
-                source(current_span(extra), extra)
-                    .parse()
-                    .expect("Internal Error: Failed to parse i64"),
-            )
+        .validate(move |_, extra, emitter| {
+            let span = current_span(extra);
+            match parse_int(source(span, extra), span, negatable) {
+                Ok((int, suffix)) => Literal::Int(int, suffix),
+                Err(diagnostic) => {
+                    emitter.emit(ParseError::diagnostic(span, diagnostic));
+                    Literal::Int(0, None)
+                }
+            }
         })
         .labelled("integer");
 
     let float = just(Token::Float)
-        .map_with(|_, extra| {
-            Literal::Float(
-                source(current_span(extra), extra)
-                    .parse()
-                    .expect("Internal Error: Failed to parse f64"),
-            )
+        .validate(|_, extra, emitter| {
+            let span = current_span(extra);
+            match parse_float(source(span, extra), span) {
+                Ok((float, suffix)) => Literal::Float(float, suffix),
+                Err(diagnostic) => {
+                    emitter.emit(ParseError::diagnostic(span, diagnostic));
+                    Literal::Float(0.0, None)
+                }
+            }
         })
         .labelled("float");
 
     let string = just(Token::String)
+        .validate(|_, extra, emitter| {
+            let mut span: Span = current_span(extra);
+            span.start += 1;
+            span.end -= 1;
+            let (string, diagnostics) = unescape(source(span, extra), span);
+            for diagnostic in diagnostics {
+                emitter.emit(ParseError::diagnostic(span, diagnostic));
+            }
+            Literal::String(string)
+        })
+        .labelled("string");
+
+    let raw_string = just(Token::RawString)
         .map_with(|_, extra| {
-            Literal::String({
-                let mut span: Span = current_span(extra);
-                span.start += 1;
-                span.end -= 1;
-                source(span, extra).into()
-            })
+            let src = source(current_span(extra), extra);
+            let pounds = src[1..].find('"').expect("Internal Error: Missing `\"`");
+            Literal::String(src[2 + pounds..src.len() - 1 - pounds].to_string())
+        })
+        .labelled("string");
+
+    let multi_line_string = just(Token::MultiLineString)
+        .validate(|_, extra, emitter| {
+            let mut span: Span = current_span(extra);
+            span.start += 3;
+            span.end -= 3;
+            let (string, diagnostics) = unindent(source(span, extra), span);
+            for diagnostic in diagnostics {
+                emitter.emit(ParseError::diagnostic(span, diagnostic));
+            }
+            Literal::String(string)
         })
         .labelled("string");
 
-    choice((integer, float, string)).labelled("literal").boxed()
+    let char = just(Token::Char)
+        .validate(|_, extra, emitter| {
+            let mut span: Span = current_span(extra);
+            span.start += 1;
+            span.end -= 1;
+            let (string, diagnostics) = unescape(source(span, extra), span);
+            let is_valid = diagnostics.is_empty();
+            for diagnostic in diagnostics {
+                emitter.emit(ParseError::diagnostic(span, diagnostic));
+            }
+
+            let mut chars = string.chars();
+            match (chars.next(), chars.next()) {
+                (Some(char), None) => Literal::Char(char),
+                _ => {
+                    if is_valid {
+                        let span = current_span(extra);
+                        emitter.emit(ParseError::diagnostic(
+                            span,
+                            Diagnostic::spanned(
+                                span,
+                                Level::Error,
+                                "Character literal must contain exactly one character",
+                            )
+                            .with_child(span, Level::Error, "Expected exactly one character")
+                            .with_child(
+                                vec![],
+                                Level::Help,
+                                "Use double quotes to create a string literal",
+                            ),
+                        ));
+                    }
+                    Literal::Char(char::REPLACEMENT_CHARACTER)
+                }
+            }
+        })
+        .labelled("character");
+
+    choice((integer, float, char, string, raw_string, multi_line_string))
+        .labelled("literal")
+        .boxed()
+}
+
+/// Parses an integer literal with an optional `0x`, `0o` or `0b` prefix,
+/// `_` separators between its digits and an optional type suffix like `u8`.
+/// See [`check_int_range`] for literals which are `negatable`.
+pub(super) fn parse_int(
+    src: &str,
+    span: Span,
+    negatable: bool,
+) -> Result<(u64, Option<NumberSuffix>), Diagnostic> {
+    let (src, suffix) = split_suffix(src, span, &['i', 'u'])?;
+    let (radix, name, digits) = match src.get(..2) {
+        Some("0x") => (16, "hexadecimal", &src[2..]),
+        Some("0o") => (8, "octal", &src[2..]),
+        Some("0b") => (2, "binary", &src[2..]),
+        _ => (10, "decimal", src),
+    };
+    let prefix_len = src.len() - digits.len();
+
+    let invalid_digit = digits
+        .char_indices()
+        .find(|(_, char)| *char != '_' && !char.is_digit(radix));
+    if let Some((index, digit)) = invalid_digit {
+        let start = span.start + prefix_len + index;
+        let digit_span = Span::new(start, start + digit.len_utf8()).with_id(span.id);
+        return Err(Diagnostic::spanned(
+            digit_span,
+            Level::Error,
+            format!("Invalid digit `{digit}` in {name} literal"),
+        ));
+    }
+
+    let digits = digits.replace('_', "");
+    if digits.is_empty() {
+        return Err(Diagnostic::spanned(
+            span,
+            Level::Error,
+            format!(
+                "Missing digits after integer prefix `{}`",
+                &src[..prefix_len]
+            ),
+        ));
+    }
+
+    // Wider than any integer type, so that the range of the suffix can be checked.
+    // Even more digits are out of range for every type.
+    let int = u128::from_str_radix(&digits, radix).unwrap_or(u128::MAX);
+    check_int_range(int, suffix, negatable, span)?;
+    Ok((int as u64, suffix))
+}
+
+/// Reports integer literals which don't fit into the type of their suffix, or into an `int`.
+/// Literals which are `negatable` can be one larger than the maximum, because `-128i8` is in range.
+pub(super) fn check_int_range(
+    int: u128,
+    suffix: Option<NumberSuffix>,
+    negatable: bool,
+    span: Span,
+) -> Result<(), Diagnostic> {
+    let (min, max) = NumberSuffix::int_range(suffix);
+    let limit = if negatable { max.max(-min) } else { max };
+    if int <= limit as u128 {
+        return Ok(());
+    }
+
+    Err(match suffix {
+        None => Diagnostic::spanned(span, Level::Error, "Integer literal is too large")
+            .with_child(span, Level::Error, "Does not fit into an int")
+            .with_child(
+                vec![],
+                Level::Note,
+                format!("The maximum value of an int is {max}"),
+            ),
+        Some(suffix) => Diagnostic::spanned(
+            span,
+            Level::Error,
+            format!("Integer literal is out of range for `{suffix}`"),
+        )
+        .with_child(span, Level::Error, format!("Does not fit into `{suffix}`"))
+        .with_child(
+            vec![],
+            Level::Note,
+            format!("The range of `{suffix}` is {min}..={max}"),
+        ),
+    })
+}
+
+/// Parses a float literal with an optional exponent,
+/// `_` separators between its digits and an optional type suffix like `f32`.
+pub(super) fn parse_float(
+    src: &str,
+    span: Span,
+) -> Result<(f64, Option<NumberSuffix>), Diagnostic> {
+    let (src, suffix) = split_suffix(src, span, &['f'])?;
+    let digits = src.replace('_', "");
+    if digits.ends_with(['e', 'E', '+', '-']) {
+        return Err(
+            Diagnostic::spanned(span, Level::Error, "Missing digits in float exponent").with_child(
+                span,
+                Level::Error,
+                "Expected at least one digit after `e`",
+            ),
+        );
+    }
+
+    let float: f64 = digits.parse().expect("Internal Error: Failed to parse f64");
+    let (float, typ, max) = match suffix {
+        Some(NumberSuffix::Float(32)) => (
+            float as f32 as f64,
+            "`f32`".to_string(),
+            format!("{:e}", f32::MAX),
+        ),
+        Some(suffix) => (float, format!("`{suffix}`"), format!("{:e}", f64::MAX)),
+        None => (float, "a float".to_string(), format!("{:e}", f64::MAX)),
+    };
+    if float.is_infinite() {
+        return Err(
+            Diagnostic::spanned(span, Level::Error, "Float literal is too large")
+                .with_child(span, Level::Error, format!("Does not fit into {typ}"))
+                .with_child(
+                    vec![],
+                    Level::Note,
+                    format!("The maximum value of {typ} is {max}"),
+                ),
+        );
+    }
+
+    Ok((float, suffix))
+}
+
+/// Splits a number literal into its digits and its type suffix,
+/// which starts at the first occurrence of one of the `suffix_starts`.
+fn split_suffix<'a>(
+    src: &'a str,
+    span: Span,
+    suffix_starts: &[char],
+) -> Result<(&'a str, Option<NumberSuffix>), Diagnostic> {
+    let Some(index) = src.find(suffix_starts) else {
+        return Ok((src, None));
+    };
+
+    match NumberSuffix::from_suffix(&src[index..]) {
+        Some(suffix) => Ok((&src[..index], Some(suffix))),
+        None => {
+            let suffix_span = Span::new(span.start + index, span.end).with_id(span.id);
+            Err(Diagnostic::spanned(
+                suffix_span,
+                Level::Error,
+                format!("Invalid suffix `{}` for number literal", &src[index..]),
+            )
+            .with_child(suffix_span, Level::Error, "Invalid suffix")
+            .with_child(
+                vec![],
+                Level::Help,
+                "Valid suffixes are `i8`, `i16`, `i32`, `i64`, `isize`, \
+                `u8`, `u16`, `u32`, `u64`, `usize`, `f32` and `f64`",
+            ))
+        }
+    }
+}
+
+/// Removes the indentation shared by all lines of a multi-line string,
+/// as well as the line breaks directly after the opening and before the closing `"""`.
+///
+/// Escape sequences get replaced line by line, so that their diagnostics still point into `src`.
+pub(super) fn unindent(src: &str, span: Span) -> (String, Vec<Diagnostic>) {
+    let is_blank = |line: &str| line.trim_start_matches([' ', '\t']).is_empty();
+
+    let mut lines = vec![];
+    let mut offset = 0;
+    for line in src.split('\n') {
+        lines.push((offset, line.strip_suffix('\r').unwrap_or(line)));
+        offset += line.len() + 1;
+    }
+    if lines.len() > 1 && is_blank(lines[lines.len() - 1].1) {
+        lines.pop();
+    }
+    // Text directly after the opening `"""` is not indented
+    let first_line = lines.remove(0);
+    let first_line = (!is_blank(first_line.1) || lines.is_empty()).then_some(first_line);
+
+    let indent = lines
+        .iter()
+        .map(|(_, line)| *line)
+        .filter(|line| !is_blank(line))
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .reduce(|indent, line_indent| {
+            let common = indent
+                .bytes()
+                .zip(line_indent.bytes())
+                .take_while(|(lhs, rhs)| lhs == rhs)
+                .count();
+            &indent[..common]
+        })
+        .unwrap_or("");
+
+    let mut result = String::with_capacity(src.len());
+    let mut diagnostics = vec![];
+    let lines = lines.into_iter().map(|(offset, line)| {
+        let unindented = line
+            .strip_prefix(indent)
+            .unwrap_or_else(|| line.trim_start_matches([' ', '\t']));
+        (offset + line.len() - unindented.len(), unindented)
+    });
+    for (index, (offset, line)) in first_line.into_iter().chain(lines).enumerate() {
+        if index > 0 {
+            result.push('\n');
+        }
+        let line_span =
+            Span::new(span.start + offset, span.start + offset + line.len()).with_id(span.id);
+        let (line, line_diagnostics) = unescape(line, line_span);
+        result.push_str(&line);
+        diagnostics.extend(line_diagnostics);
+    }
+
+    (result, diagnostics)
+}
+
+/// Replaces all escape sequences in `src` with the characters they represent.
+///
+/// `span` has to point to `src` and is used to report invalid escape sequences,
+/// which get skipped so that the rest of the literal can still be checked.
+pub(super) fn unescape(src: &str, span: Span) -> (String, Vec<Diagnostic>) {
+    let mut result = String::with_capacity(src.len());
+    let mut diagnostics = vec![];
+    let mut rest = src;
+
+    while let Some(index) = rest.find('\\') {
+        result.push_str(&rest[..index]);
+        let start = span.start + (src.len() - rest.len()) + index;
+        let len = match unescape_sequence(&rest[index..]) {
+            Ok((char, len)) => {
+                result.push(char);
+                len
+            }
+            Err((len, message)) => {
+                let escape_span = Span::new(start, start + len).with_id(span.id);
+                diagnostics.push(Diagnostic::spanned(escape_span, Level::Error, message));
+                len
+            }
+        };
+        rest = &rest[index + len..];
+    }
+    result.push_str(rest);
+
+    (result, diagnostics)
+}
+
+/// Decodes the escape sequence at the start of `src`,
+/// returns the character and the length of the sequence in bytes.
+fn unescape_sequence(src: &str) -> Result<(char, usize), (usize, String)> {
+    let char = match src[1..].chars().next() {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('0') => '\0',
+        Some('\\') => '\\',
+        Some('"') => '"',
+        Some('\'') => '\'',
+        Some('u') => return unescape_unicode(src),
+        Some(other) => {
+            return Err((
+                1 + other.len_utf8(),
+                format!("Unknown character escape `\\{}`", other.escape_debug()),
+            ))
+        }
+        None => return Err((1, "Expected character after `\\`".into())),
+    };
+    Ok((char, 2))
+}
+
+/// Decodes an unicode escape sequence of the form `\u{7FFF}`
+fn unescape_unicode(src: &str) -> Result<(char, usize), (usize, String)> {
+    let Some(digits) = src[2..].strip_prefix('{') else {
+        return Err((2, "Expected `{` after `\\u`".into()));
+    };
+    let digits_len = digits
+        .find(|char: char| !char.is_ascii_hexdigit())
+        .unwrap_or(digits.len());
+    if !digits[digits_len..].starts_with('}') {
+        return Err((
+            3 + digits_len,
+            "Unterminated unicode escape, expected `}`".into(),
+        ));
+    }
+
+    let len = 4 + digits_len;
+    let digits = &digits[..digits_len];
+    if digits.is_empty() {
+        return Err((
+            len,
+            "Empty unicode escape, expected at least one hex digit".into(),
+        ));
+    }
+    if digits.len() > 6 {
+        return Err((len, "Unicode escape must have at most 6 hex digits".into()));
+    }
+
+    let value = u32::from_str_radix(digits, 16).expect("Internal Error: Failed to parse u32");
+    match char::from_u32(value) {
+        Some(char) => Ok((char, len)),
+        None => Err((
+            len,
+            format!("Invalid unicode escape, `{value:X}` is not a character"),
+        )),
+    }
+}
+
+pub(super) fn doc_comment_parser<'src>(
+) -> impl Parser<'src, TokenStream<'src>, Option<DocComment>, Extra<'src>> + Clone {
+    // doc_comment ::= ("///" ...)*
+    just(Token::DocComment)
+        .map_with(|_, extra| {
+            let line = source(current_span(extra), extra)[3..].trim_end_matches('\r');
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .repeated()
+        .collect::<Vec<&str>>()
+        .map(|lines| (!lines.is_empty()).then(|| lines.join("\n")))
+        .labelled("doc comment")
 }
 
 pub(super) fn ident_parser<'src>(
-) -> impl Parser<'src, TokenStream<'src>, &'src str, Extra<'src>> + Clone {
+) -> impl Parser<'src, TokenStream<'src>, String, Extra<'src>> + Clone {
+    // Normalizing, so that identifiers which look the same also compare equal
     just(Token::Ident)
-        .map_with(|_, extra| source(current_span(extra), extra))
+        .map_with(|_, extra| source(current_span(extra), extra).nfc().collect())
         .labelled("identifier")
 }
 
+/// Parses the name of a field after a `.`, tuple fields are named by their index.
+/// `tuple.0.1` is tokenized as a float, so it names two fields at once.
+pub(super) fn field_parser<'src>(
+) -> impl Parser<'src, TokenStream<'src>, Vec<Spanned<String>>, Extra<'src>> + Clone {
+    let index = one_of([Token::Int, Token::Float])
+        .validate(|_, extra, emitter| {
+            let span = current_span(extra);
+            let mut start = span.start;
+            let mut fields = vec![];
+            for index in source(span, extra).split('.') {
+                let index_span = Span::new(start, start + index.len()).with_id(span.id);
+                start = index_span.end + 1;
+                if index.is_empty() || !index.bytes().all(|byte| byte.is_ascii_digit()) {
+                    emitter.emit(ParseError::diagnostic(
+                        index_span,
+                        Diagnostic::spanned(
+                            index_span,
+                            Level::Error,
+                            format!("Invalid tuple index `{index}`"),
+                        )
+                        .with_child(
+                            vec![],
+                            Level::Help,
+                            "Tuple fields are accessed with their position, like `tuple.0`",
+                        ),
+                    ));
+                }
+                fields.push(Spanned::new(index.to_string(), index_span));
+            }
+            fields
+        })
+        .labelled("tuple index");
+
+    // field ::= ident | int
+    ident_parser()
+        .map_with(|name, extra| vec![spanned(name, extra)])
+        .or(index)
+}
+
+/// Recovers from an error between `open` and `close` by skipping to the matching `close`
+pub(super) fn recover_delimited<'src, O>(
+    open: Token,
+    close: Token,
+    fallback: impl Fn(Span) -> O + Clone,
+) -> impl Parser<'src, TokenStream<'src>, O, Extra<'src>> + Clone {
+    nested_delimiters(
+        open,
+        close,
+        [
+            (Token::ParenOpen, Token::ParenClose),
+            (Token::BracketOpen, Token::BracketClose),
+            (Token::BraceOpen, Token::BraceClose),
+        ],
+        fallback,
+    )
+}
+
+/// Recovers from an error in a statement by skipping to the next `;` or definition,
+/// without leaving the enclosing block
+pub(super) fn recover_stmt<'src>(
+) -> impl Parser<'src, TokenStream<'src>, Spanned<Stmt>, Extra<'src>> + Clone {
+    let token = choice((
+        recover_delimited(Token::ParenOpen, Token::ParenClose, |_| ()),
+        recover_delimited(Token::BracketOpen, Token::BracketClose, |_| ()),
+        recover_delimited(Token::BraceOpen, Token::BraceClose, |_| ()),
+        none_of([
+            Token::Semi,
+            Token::ParenOpen,
+            Token::ParenClose,
+            Token::BracketOpen,
+            Token::BracketClose,
+            Token::BraceOpen,
+            Token::BraceClose,
+        ])
+        .ignored(),
+    ));
+    let definition_start = one_of([Token::Const, Token::Let, Token::Fn, Token::Type]);
+
+    // Always skipping the first token, otherwise a broken definition would never be skipped.
+    // Not recovering at the end of a block, because the statement is likely its final expression.
+    token
+        .clone()
+        .then(token.and_is(definition_start.not()).repeated())
+        .then(choice((
+            just(Token::Semi).ignored(),
+            definition_start.rewind().ignored(),
+        )))
+        .ignored()
+        .or(just(Token::Semi).ignored())
+        .to(Stmt::Error)
+        .map_with(spanned)
+}
+
 pub(super) fn current_span<'a>(extra: &mut MapExtra<'a, '_, TokenStream<'a>, Extra<'a>>) -> Span {
     extra.span()
 }
 
+pub(super) fn spanned<'a, T>(
+    val: T,
+    extra: &mut MapExtra<'a, '_, TokenStream<'a>, Extra<'a>>,
+) -> Spanned<T> {
+    Spanned::new(val, extra.span())
+}
+
 pub(super) fn source<'a>(
     span: Span,
     extra: &mut MapExtra<'a, '_, TokenStream<'a>, Extra<'a>>,
@@ -99,7 +99,7 @@ impl Reason {
                     found,
                 }
             }
-            (Reason::Custom(this), Reason::Custom(other)) => todo!(),
+            // Keeps the earlier diagnostic, which is the more specific one
             (this @ Reason::Custom(_), _) => this,
             (_, other @ Reason::Custom(_)) => other,
         }
@@ -125,6 +125,16 @@ impl ParseError {
         }
     }
 
+    /// Create an error from an already constructed [`Diagnostic`]
+    #[inline]
+    pub fn diagnostic(span: Span, diagnostic: Diagnostic) -> Self {
+        ParseError {
+            span,
+            reason: Reason::Custom(diagnostic),
+            context: Vec::new(),
+        }
+    }
+
     /// Get the span associated with this error.
     pub fn span(&self) -> Span {
         self.span
@@ -218,7 +228,8 @@ impl<'a> chumsky::error::Error<'a, TokenStream<'a>> for ParseError {
                     }
                 }
             }
-            Reason::Custom(_) => todo!(),
+            // The diagnostic already explains the error, expected tokens would not add anything
+            Reason::Custom(_) => {}
         }
         // TOOD: Merge contexts
         self
@@ -1,49 +1,294 @@
-use super::{common::*, error::ParseError, stmt::stmt_parser};
-use crate::ast::*;
+use super::{common::*, error::ParseError, expr_parser, stmt_parser, type_parser};
+use crate::{
+    ast::*,
+    error::{Diagnostic, Level},
+    span::Spanned,
+    tokenize::Token,
+};
 use chumsky::prelude::*;
 
+enum Item {
+    Stmt(Spanned<Stmt>, Visibility),
+    Impl(Impl),
+    Module(Module),
+    Use(Use),
+    Test(Test),
+}
+
 pub fn file_parser(src: &str) -> impl Parser<TokenStream, Module, Extra> {
-    let definition = stmt_parser(src).validate(|stmt, extra, emitter| {
-        match stmt {
+    let stmt = stmt_parser(src);
+    let expr = expr_parser(stmt.clone());
+
+    let definition = stmt.clone().validate(|stmt, _, emitter| {
+        match stmt.val {
             Stmt::Expr(..) => emitter.emit(ParseError::custom(
-                current_span(extra),
+                stmt.span,
                 "Top-level expressions are not allowed.",
             )),
-            Stmt::Variable(VariableKind::Let | VariableKind::LetMut, ..) => {
-                emitter.emit(ParseError::custom(current_span(extra), "todo"))
+            Stmt::Variable(kind @ (VariableKind::Let | VariableKind::LetMut), ref name, ..) => {
+                emitter.emit(ParseError::diagnostic(
+                    stmt.span,
+                    Diagnostic::spanned(
+                        stmt.span,
+                        Level::Error,
+                        format!("Variables can't be declared with `{kind}` outside of functions"),
+                    )
+                    .with_child(stmt.span, Level::Error, "Only allowed inside of functions")
+                    .with_child(
+                        vec![],
+                        Level::Help,
+                        format!("Declare a constant instead: `const {} = ..`", name.val),
+                    ),
+                ))
             }
             _ => {}
         }
         stmt
     });
 
-    // file ::= (definition)*
-    definition
-        .repeated()
-        .collect()
-        .map(|stmts: Vec<Stmt>| {
+    // visibility ::= ("pub" ("(" ("super" | "package") ")")?)?
+    let visibility = just(Token::Pub)
+        .ignore_then(
+            ident_parser()
+                .map_with(spanned)
+                .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+                .or_not(),
+        )
+        .validate(|scope, _, emitter| match scope {
+            None => Visibility::Public,
+            Some(scope) => match scope.val.as_str() {
+                "super" => Visibility::Super,
+                "package" => Visibility::Package,
+                _ => {
+                    emitter.emit(ParseError::diagnostic(
+                        scope.span,
+                        Diagnostic::spanned(
+                            scope.span,
+                            Level::Error,
+                            format!("Unknown visibility `pub({})`", scope.val),
+                        )
+                        .with_child(
+                            vec![],
+                            Level::Help,
+                            "Use `pub`, `pub(package)` or `pub(super)`",
+                        ),
+                    ));
+                    Visibility::Public
+                }
+            },
+        })
+        .or_not()
+        .map(|visibility| visibility.unwrap_or(Visibility::Private))
+        .labelled("visibility");
+
+    let impl_item = visibility.clone().then(stmt.validate(|stmt, _, emitter| {
+        match stmt.val {
+            Stmt::Variable(VariableKind::Const, ..) | Stmt::Error => {}
+            _ => emitter.emit(ParseError::diagnostic(
+                stmt.span,
+                Diagnostic::spanned(
+                    stmt.span,
+                    Level::Error,
+                    "Only constants and functions are allowed in impl blocks",
+                ),
+            )),
+        }
+        stmt
+    }));
+
+    // impl ::= doc_comment "impl" generic_params? type "{" (visibility (const | function_def))* "}"
+    let impl_ = doc_comment_parser()
+        .then_ignore(just(Token::Impl))
+        .then(
+            generic_params_parser(type_parser())
+                .or_not()
+                .map(Option::unwrap_or_default),
+        )
+        .then(type_parser())
+        .then(
+            impl_item
+                .repeated()
+                .collect::<Vec<(Visibility, Spanned<Stmt>)>>()
+                .delimited_by(just(Token::BraceOpen), just(Token::BraceClose))
+                .recover_with(via_parser(recover_delimited(
+                    Token::BraceOpen,
+                    Token::BraceClose,
+                    |_| vec![],
+                ))),
+        )
+        .validate(|(((doc, generics), typ), stmts), _, emitter| {
             let mut constants = vec![];
-            let mut types = vec![];
+            for (visibility, stmt) in stmts {
+                if let Stmt::Variable(VariableKind::Const, name, typ, rhs, doc) = stmt.val {
+                    constants.push((name, typ, rhs, doc, visibility));
+                }
+            }
+            let item = Impl {
+                generics,
+                typ,
+                constants,
+                doc,
+            };
+            if item.type_name().is_none() {
+                emitter.emit(ParseError::diagnostic(
+                    item.typ.span,
+                    Diagnostic::spanned(
+                        item.typ.span,
+                        Level::Error,
+                        "Only named types can have impl blocks",
+                    ),
+                ));
+            }
+            item
+        })
+        .labelled("impl");
+
+    // use ::= "use" ident ("." ident)* ("." "{" ident ("," ident)* "}")? ";"
+    let use_ = just(Token::Use)
+        .ignore_then(
+            ident_parser()
+                .map_with(spanned)
+                .separated_by(just(Token::Dot))
+                .at_least(1)
+                .collect::<Vec<Spanned<String>>>(),
+        )
+        .then(
+            just(Token::Dot)
+                .ignore_then(
+                    ident_parser()
+                        .map_with(spanned)
+                        .separated_by(just(Token::Comma))
+                        .allow_trailing()
+                        .at_least(1)
+                        .collect::<Vec<Spanned<String>>>()
+                        .delimited_by(just(Token::BraceOpen), just(Token::BraceClose)),
+                )
+                .or_not(),
+        )
+        .then_ignore(just(Token::Semi))
+        .validate(|(mut path, items), extra, emitter| {
+            let items = match items {
+                Some(items) => items,
+                // Checked by `at_least(1)`
+                None => vec![path.pop().unwrap()],
+            };
+            if path.is_empty() {
+                let span = current_span(extra);
+                emitter.emit(ParseError::diagnostic(
+                    span,
+                    Diagnostic::spanned(span, Level::Error, "Missing the module to use items from")
+                        .with_child(
+                            vec![],
+                            Level::Help,
+                            "Name the module of the item, like `use super.module.item`",
+                        ),
+                ));
+            }
+            Use { path, items }
+        })
+        .labelled("use");
 
-            for stmt in stmts {
-                match stmt {
-                    Stmt::Variable(VariableKind::Const, name, typ, rhs) => {
-                        constants.push((name, typ, rhs))
+    // test ::= doc_comment "test" string block
+    let test = doc_comment_parser()
+        .then_ignore(ident_parser().filter(|name| name == "test"))
+        .then(
+            literal_parser()
+                .try_map(|literal, span| match literal {
+                    Literal::String(name) => Ok(name),
+                    _ => Err(ParseError::diagnostic(
+                        span,
+                        Diagnostic::spanned(span, Level::Error, "Test names have to be strings")
+                            .with_child(span, Level::Error, "Expected a string"),
+                    )),
+                })
+                .map_with(spanned),
+        )
+        .then(just(Token::BraceOpen).rewind().ignore_then(expr))
+        .map(|((doc, name), body)| Test { name, body, doc })
+        .labelled("test");
+
+    // file ::= (impl | use | test | visibility (module | definition))*
+    recursive(|items| {
+        // module ::= doc_comment "module" ident (";" | "{" file "}")
+        let module = doc_comment_parser()
+            .then_ignore(just(Token::Module))
+            .then(ident_parser().map_with(spanned))
+            .then(choice((
+                just(Token::Semi).to(None),
+                items
+                    .delimited_by(just(Token::BraceOpen), just(Token::BraceClose))
+                    .recover_with(via_parser(recover_delimited(
+                        Token::BraceOpen,
+                        Token::BraceClose,
+                        |span| Module::new(Spanned::new(String::new(), span)),
+                    )))
+                    .map(Some),
+            )))
+            .map(
+                |((doc, name), inner): ((Option<DocComment>, Spanned<String>), Option<Module>)| {
+                    match inner {
+                        Some(inner) => Module { name, doc, ..inner },
+                        None => Module {
+                            doc,
+                            file: Some(Spanned::new(format!("{}.rym", name.val), name.span)),
+                            ..Module::new(name)
+                        },
+                    }
+                },
+            )
+            .labelled("module");
+
+        choice((
+            impl_.clone().map(Item::Impl),
+            use_.clone().map(Item::Use),
+            test.clone().map(Item::Test),
+            visibility.clone().then(module).map(|(visibility, module)| {
+                Item::Module(Module {
+                    visibility,
+                    ..module
+                })
+            }),
+            visibility
+                .clone()
+                .then(definition.clone())
+                .map(|(visibility, stmt)| Item::Stmt(stmt, visibility)),
+        ))
+        .repeated()
+        .collect()
+        .map_with(|items: Vec<Item>, extra| {
+            let mut module = Module::new(spanned(String::new(), extra));
+            for item in items {
+                let (stmt, visibility) = match item {
+                    Item::Stmt(stmt, visibility) => (stmt, visibility),
+                    Item::Impl(inner) => {
+                        module.impls.push(inner);
+                        continue;
+                    }
+                    Item::Module(inner) => {
+                        module.sub_modules.push(inner);
+                        continue;
+                    }
+                    Item::Use(inner) => {
+                        module.uses.push(inner);
+                        continue;
+                    }
+                    Item::Test(inner) => {
+                        module.tests.push(inner);
+                        continue;
                     }
-                    Stmt::Type(name, rhs) => types.push((name, rhs)),
+                };
+                match stmt.val {
+                    Stmt::Variable(VariableKind::Const, name, typ, rhs, doc) => {
+                        module.constants.push((name, typ, rhs, doc, visibility))
+                    }
+                    Stmt::Type(name, rhs, doc) => module.types.push((name, rhs, doc, visibility)),
 
                     // Already emitted an error for these
                     _ => {}
                 }
             }
-
-            Module {
-                // TODO
-                name: "".into(),
-                constants,
-                types,
-                sub_modules: vec![],
-            }
+            module
         })
-        .with_ctx(src)
+    })
+    .with_ctx(src)
 }
@@ -0,0 +1,159 @@
+use super::{common::*, error::ParseError};
+use crate::{
+    ast::*,
+    error::{Diagnostic, Level},
+    span::Spanned,
+    tokenize::Token,
+};
+use chumsky::prelude::*;
+
+pub fn pattern_parser<'src>(
+) -> impl Parser<'src, TokenStream<'src>, Spanned<Pattern>, Extra<'src>> + Clone {
+    recursive(|pattern| {
+        // literal ::= int | float | string
+        let literal = literal_parser().map(Pattern::Literal).map_with(spanned);
+
+        // tuple ::= "(" (pattern ",")* pattern? ")"
+        let tuple = pattern
+            .clone()
+            .then_ignore(just(Token::Comma))
+            .repeated()
+            .collect::<Vec<Spanned<Pattern>>>()
+            .then(pattern.clone().or_not())
+            .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+            .map_with(|(mut items, last), extra| match last {
+                // Without a comma the parentheses only group
+                Some(last) if items.is_empty() => last,
+                Some(last) => {
+                    items.push(last);
+                    spanned(Pattern::Tuple(items), extra)
+                }
+                None => spanned(Pattern::Tuple(items), extra),
+            })
+            .labelled("tuple pattern");
+
+        // struct_field ::= ident ("=" pattern)?
+        let struct_field = ident_parser()
+            .map_with(spanned)
+            .then(just(Token::Assign).ignore_then(pattern.clone()).or_not())
+            .map(|(name, pattern)| {
+                // Shorthand for `name = name`
+                let pattern = pattern
+                    .unwrap_or_else(|| Spanned::new(Pattern::Binding(name.val.clone()), name.span));
+                (name, pattern)
+            });
+
+        // struct ::= path "{" (struct_field ("," struct_field)* ","?)? "}"
+        let struct_ = path_parser()
+            .then(
+                struct_field
+                    .separated_by(just(Token::Comma))
+                    .allow_trailing()
+                    .collect::<Vec<(Spanned<String>, Spanned<Pattern>)>>()
+                    .delimited_by(just(Token::BraceOpen), just(Token::BraceClose)),
+            )
+            .map(|(path, fields)| Pattern::Struct(path, fields))
+            .map_with(spanned)
+            .labelled("struct pattern");
+
+        // variant ::= path "(" pattern ")"
+        let variant = path_parser()
+            .then(
+                pattern
+                    .clone()
+                    .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
+            )
+            .map(|(path, inner)| Pattern::EnumVariant(path, Some(Box::new(inner))))
+            .map_with(spanned)
+            .labelled("enum variant pattern");
+
+        // A single identifier binds, a longer path names an enum variant without a value
+        // binding ::= path
+        let binding = path_parser()
+            .map(|path| match &path.parts[..] {
+                [name] if name == "_" => Pattern::Wildcard,
+                [name] => Pattern::Binding(name.clone()),
+                _ => Pattern::EnumVariant(path, None),
+            })
+            .map_with(spanned);
+
+        // atom ::= literal | tuple | struct | variant | binding
+        let atom = choice((literal, tuple, struct_, variant, binding));
+
+        // at ::= atom ("@" ident)?
+        let at = atom
+            .then(
+                just(Token::At)
+                    .ignore_then(ident_parser().map_with(spanned))
+                    .or_not(),
+            )
+            .map_with(|(inner, name), extra| match name {
+                Some(name) => spanned(Pattern::At(Box::new(inner), name), extra),
+                None => inner,
+            });
+
+        // or ::= at ("|" at)*
+        at.separated_by(just(Token::Pipe))
+            .at_least(1)
+            .collect::<Vec<Spanned<Pattern>>>()
+            .validate(|mut alternatives, extra, emitter| {
+                if alternatives.len() == 1 {
+                    return alternatives.remove(0);
+                }
+                let expected = Pattern::bindings(&alternatives[0]);
+                for alternative in &alternatives[1..] {
+                    let found = Pattern::bindings(alternative);
+                    let missing = |names: &[Spanned<String>], other: &[Spanned<String>]| {
+                        names
+                            .iter()
+                            .filter(|name| other.iter().all(|other| other.val != name.val))
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    };
+                    for (name, without) in missing(&expected, &found)
+                        .into_iter()
+                        .map(|name| (name, alternative.span))
+                        .chain(
+                            missing(&found, &expected)
+                                .into_iter()
+                                .map(|name| (name, alternatives[0].span)),
+                        )
+                    {
+                        emitter.emit(ParseError::diagnostic(
+                            name.span,
+                            Diagnostic::new(
+                                Level::Error,
+                                format!("`{name}` is not bound in all alternatives"),
+                            )
+                            .with_child(name.span, Level::Error, "Bound here")
+                            .with_child(
+                                without,
+                                Level::Error,
+                                "But not in this alternative",
+                            ),
+                        ));
+                    }
+                }
+                spanned(Pattern::Or(alternatives), extra)
+            })
+    })
+    .validate(|pattern, _, emitter| {
+        let bindings = Pattern::bindings(&pattern);
+        for (index, name) in bindings.iter().enumerate() {
+            let Some(first) = bindings[..index].iter().find(|prev| prev.val == name.val) else {
+                continue;
+            };
+            emitter.emit(ParseError::diagnostic(
+                name.span,
+                Diagnostic::new(
+                    Level::Error,
+                    format!("`{name}` is bound more than once in the same pattern"),
+                )
+                .with_child(name.span, Level::Error, "Bound again here")
+                .with_child(first.span, Level::Note, "First bound here"),
+            ));
+        }
+        pattern
+    })
+    .labelled("pattern")
+}
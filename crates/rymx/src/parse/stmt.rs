@@ -1,43 +1,67 @@
-use super::{common::*, error::ParseError, type_parser};
-use crate::{ast::*, tokenize::Token};
+use super::{common::*, error::ParseError, pattern_parser, type_parser};
+use crate::{
+    ast::*,
+    error::{Diagnostic, Level},
+    span::Spanned,
+    tokenize::Token,
+};
 use chumsky::{prelude::*, util::MaybeRef};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+
+/// Positional and named arguments of a call
+type Arguments = (Vec<Spanned<Expr>>, Vec<(Spanned<String>, Spanned<Expr>)>);
 
 macro_rules! struct_expr {
     ($typ:expr, $($key:ident : $val:expr),+ $(,)?) => {
         Expr::Struct(
             Path::new(vec![$typ.into()]),
-            vec![$((stringify!($key).to_string(), $val),)*],
+            vec![],
+            vec![$((Spanned::new(stringify!($key).to_string(), $val.span), $val),)*],
         )
     };
 }
 
-pub fn stmt_parser(src: &str) -> impl Parser<TokenStream, Stmt, Extra> + Clone {
+pub fn stmt_parser(src: &str) -> impl Parser<TokenStream, Spanned<Stmt>, Extra> + Clone {
     recursive(|stmt| {
         let expr = expr_parser(stmt.clone());
 
-        // type_def ::=  "type" ident "=" type ";"
-        let type_def = just(Token::Type)
-            .ignore_then(ident_parser())
+        // type_def ::= doc_comment "type" ident "=" type ";"
+        let type_def = doc_comment_parser()
+            .then_ignore(just(Token::Type))
+            .then(ident_parser().map_with(spanned))
             .then_ignore(just(Token::Assign))
             .then(type_parser())
             .then_ignore(just(Token::Semi))
-            .map(|(name, rhs)| Stmt::Type(name.into(), rhs))
+            .map(|((doc, name), rhs)| Stmt::Type(name, rhs, doc))
             .labelled("type definition")
             .boxed();
 
-        // function_def ::= "fn" ident "(" parameters ")" type? "=>" expr ";"?
-        let function_def = just(Token::Fn)
-            .ignore_then(ident_parser())
-            .then(parameters_parser().delimited_by(just(Token::ParenOpen), just(Token::ParenClose)))
-            .then(type_parser().or_not())
+        // function_def ::= doc_comment "fn" ident generic_params? "(" parameters ")" type? "=>" expr ";"?
+        let function_def = doc_comment_parser()
+            .then_ignore(just(Token::Fn))
+            .then(ident_parser().map_with(spanned))
+            .then(
+                generic_params_parser(type_parser())
+                    .or_not()
+                    .map(Option::unwrap_or_default),
+            )
+            .then(
+                parameters_parser(expr.clone())
+                    .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+                    .recover_with(via_parser(recover_delimited(
+                        Token::ParenOpen,
+                        Token::ParenClose,
+                        |_| (vec![], BTreeMap::new()),
+                    ))),
+            )
+            .then(return_type_parser(Type::Unit))
             .then_ignore(just(Token::ThickArrow))
             .then(expr_parser(stmt))
             .then(just(Token::Semi).or_not().map(|semi| semi.is_none()))
             .validate(|((rest, body), missing_semi), extra, emitter| {
                 // Not emitting "missing semicolon error" for functions
                 // that use a block expression as body
-                if missing_semi && !matches!(body, Expr::Block(..)) {
+                if missing_semi && !body.val.ends_with_block() {
                     emitter.emit(ParseError::expected_found(
                         [Some(MaybeRef::Val(Token::Semi))],
                         None,
@@ -50,73 +74,140 @@ pub fn stmt_parser(src: &str) -> impl Parser<TokenStream, Stmt, Extra> + Clone {
                 }
                 (rest, body)
             })
-            .map(|(((name, params), maybe_type), body)| {
-                Stmt::Variable(
-                    VariableKind::Const,
-                    name.into(),
-                    Type::Unkown, // TODO Use proper function type
-                    Expr::Function(Function {
-                        params,
-                        named_params: HashMap::new(),
-                        return_type: maybe_type.unwrap_or(Type::Unit),
-                        body: Box::new(body),
-                    }),
-                )
-            })
+            .map_with(
+                |(((((doc, name), generics), (params, named_params)), return_type), body),
+                 extra| {
+                    Stmt::Variable(
+                        VariableKind::Const,
+                        name.clone(),
+                        Spanned::new(Type::Unkown, name.span), // TODO Use proper function type
+                        spanned(
+                            Expr::Function(Box::new(Function {
+                                generics,
+                                params,
+                                named_params,
+                                return_type,
+                                body: Box::new(body),
+                            })),
+                            extra,
+                        ),
+                        doc,
+                    )
+                },
+            )
             .labelled("function definition");
 
-        // variable ::= ("const" | "let" | "let mut") ident (":" type)? "=" expr ";"
-        let variable = choice((
-            just(Token::Const).to(VariableKind::Const),
-            just(Token::Let)
-                .then(just(Token::Mut))
-                .to(VariableKind::LetMut),
-            just(Token::Let).to(VariableKind::Let),
-        ))
-        .then(ident_parser())
-        .then(just(Token::Colon).ignore_then(type_parser()).or_not())
-        .then_ignore(just(Token::Assign))
-        .then(expr.clone())
-        .then_ignore(just(Token::Semi))
-        .map(|(((kind, name), typ), rhs)| {
-            Stmt::Variable(kind, name.into(), typ.unwrap_or(Type::Unkown), rhs)
-        })
-        .labelled("variable definition")
-        .boxed();
+        // variable ::= doc_comment ("const" | "let" | "let mut") ident (":" type)? "=" expr ";"
+        let variable = doc_comment_parser()
+            .then(choice((
+                just(Token::Const).to(VariableKind::Const),
+                just(Token::Let)
+                    .then(just(Token::Mut))
+                    .to(VariableKind::LetMut),
+                just(Token::Let).to(VariableKind::Let),
+            )))
+            .then(ident_parser().map_with(spanned))
+            .then(just(Token::Colon).ignore_then(type_parser()).or_not())
+            .then_ignore(just(Token::Assign))
+            .then(expr.clone())
+            .then_ignore(just(Token::Semi))
+            .map(|((((doc, kind), name), typ), rhs)| {
+                let typ = typ.unwrap_or(Spanned::new(Type::Unkown, name.span));
+                Stmt::Variable(kind, name, typ, rhs, doc)
+            })
+            .labelled("variable definition")
+            .boxed();
+
+        // Loops ending with a block don't need a semicolon
+        // loop_stmt ::= (ident ":")? ("loop" | "while" | "for") expr ";"?
+        let loop_stmt = ident_parser()
+            .then(just(Token::Colon))
+            .or_not()
+            .then(one_of([Token::Loop, Token::While, Token::For]))
+            .rewind()
+            .ignore_then(expr.clone())
+            .filter(|expr: &Spanned<Expr>| expr.val.ends_with_block())
+            .then_ignore(just(Token::Semi).or_not())
+            .map(Stmt::Expr)
+            .labelled("expression");
 
         choice((
+            loop_stmt,
             expr.then_ignore(just(Token::Semi)).map(Stmt::Expr),
             type_def,
             function_def,
             variable,
         ))
+        .map_with(spanned)
+        .recover_with(via_parser(recover_stmt()))
     })
     .with_ctx(src)
 }
 
-/// Only works when called from stmt_parser!
-fn expr_parser<'src>(
-    stmt: impl Parser<'src, TokenStream<'src>, Stmt, Extra<'src>> + Clone + 'src,
-) -> impl Parser<'src, TokenStream<'src>, Expr, Extra<'src>> + Clone {
+/// The return type of a function, which defaults to `default` right after the parameters
+fn return_type_parser<'src>(
+    default: Type,
+) -> impl Parser<'src, TokenStream<'src>, Spanned<Type>, Extra<'src>> + Clone {
+    type_parser().or_not().map_with(move |typ, extra| {
+        typ.unwrap_or_else(|| {
+            let mut span = current_span(extra);
+            span.end = span.start;
+            Spanned::new(default.clone(), span)
+        })
+    })
+}
+
+/// The expression at the start of a chain of field accesses, calls and subscripts
+fn chain_start(expr: &Spanned<Expr>) -> &Spanned<Expr> {
+    match &expr.val {
+        Expr::FieldAccess(lhs, _)
+        | Expr::Call(lhs, ..)
+        | Expr::Subscript(lhs, _)
+        | Expr::Generic(lhs, _) => chain_start(lhs),
+        _ => expr,
+    }
+}
+
+/// Only works when called with the parser from [`stmt_parser`]!
+pub(super) fn expr_parser<'src>(
+    stmt: impl Parser<'src, TokenStream<'src>, Spanned<Stmt>, Extra<'src>> + Clone + 'src,
+) -> impl Parser<'src, TokenStream<'src>, Spanned<Expr>, Extra<'src>> + Clone {
     recursive(|expr| {
         // literal ::= int | float | string
-        let literal = literal_parser().map(Expr::Literal);
+        let literal = negatable_literal_parser()
+            .map(Expr::Literal)
+            .map_with(spanned);
 
-        // function ::= fn "(" parameters ")" type? "=>" expr
+        // function ::= "fn" generic_params? "(" parameters ")" type? "=>" expr
         let function = just(Token::Fn)
             .ignore_then(
-                parameters_parser().delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
+                generic_params_parser(type_parser())
+                    .or_not()
+                    .map(Option::unwrap_or_default),
             )
-            .then(type_parser().or_not())
+            .then(
+                parameters_parser(expr.clone())
+                    .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+                    .recover_with(via_parser(recover_delimited(
+                        Token::ParenOpen,
+                        Token::ParenClose,
+                        |_| (vec![], BTreeMap::new()),
+                    ))),
+            )
+            .then(return_type_parser(Type::Unkown))
             .then(just(Token::ThickArrow).ignore_then(expr.clone()))
-            .map(|((params, return_type), body)| {
-                Expr::Function(Function {
-                    params,
-                    named_params: HashMap::new(),
-                    return_type: return_type.unwrap_or(Type::Unkown),
-                    body: Box::new(body),
-                })
-            })
+            .map(
+                |(((generics, (params, named_params)), return_type), body)| {
+                    Expr::Function(Box::new(Function {
+                        generics,
+                        params,
+                        named_params,
+                        return_type,
+                        body: Box::new(body),
+                    }))
+                },
+            )
+            .map_with(spanned)
             .labelled("function");
 
         // array ::= "[" (expr ";" expr | (expr ",")* expr?) "]"
@@ -128,10 +219,16 @@ fn expr_parser<'src>(
             expr.clone()
                 .separated_by(just(Token::Comma))
                 .allow_trailing()
-                .collect::<Vec<Expr>>()
+                .collect::<Vec<Spanned<Expr>>>()
                 .map(Expr::Array),
         ))
         .delimited_by(just(Token::BracketOpen), just(Token::BracketClose))
+        .map_with(spanned)
+        .recover_with(via_parser(recover_delimited(
+            Token::BracketOpen,
+            Token::BracketClose,
+            |span| Spanned::new(Expr::Error, span),
+        )))
         .labelled("array")
         .boxed();
 
@@ -139,135 +236,478 @@ fn expr_parser<'src>(
         let block = stmt
             .clone()
             .repeated()
-            .collect::<Vec<Stmt>>()
+            .collect::<Vec<Spanned<Stmt>>>()
             .then(expr.clone().or_not())
             .delimited_by(just(Token::BraceOpen), just(Token::BraceClose))
-            .map(|(mut statements, final_expr)| {
-                if let Some(expr) = final_expr {
-                    if !matches!(expr, Expr::Return(..) | Expr::Break(..)) {
-                        statements.push(Stmt::Expr(Expr::Break(Box::new(expr))));
+            .map(|(mut statements, mut final_expr)| {
+                // A trailing `loop { .. }` without a semicolon is the value of the block
+                if final_expr.is_none() {
+                    if let Some(Spanned {
+                        val: Stmt::Expr(expr),
+                        span,
+                    }) = statements.last()
+                    {
+                        if expr.span == *span && expr.val.ends_with_block() {
+                            final_expr = Some(expr.clone());
+                            statements.pop();
+                        }
                     }
                 }
-                Expr::Block(statements)
+                Expr::Block(statements, final_expr.map(Box::new))
             })
+            .map_with(spanned)
+            .recover_with(via_parser(recover_delimited(
+                Token::BraceOpen,
+                Token::BraceClose,
+                |span| Spanned::new(Expr::Error, span),
+            )))
             .labelled("block")
             .boxed();
 
-        // atom ::= literal | ident | array | "(" expr ")" | block
+        // generic_args ::= "[" type ("," type)* ","? "]"
+        let generic_args = type_parser()
+            .separated_by(just(Token::Comma))
+            .allow_trailing()
+            .at_least(1)
+            .collect::<Vec<Spanned<Type>>>()
+            .delimited_by(just(Token::BracketOpen), just(Token::BracketClose))
+            .labelled("generic arguments")
+            .boxed();
+
+        // struct_field ::= ident ("=" expr)?
+        let struct_field = ident_parser()
+            .map_with(spanned)
+            .then(just(Token::Assign).ignore_then(expr.clone()).or_not())
+            .map(|(name, value)| {
+                // Shorthand for `name = name`
+                let value =
+                    value.unwrap_or_else(|| Spanned::new(Expr::Ident(name.val.clone()), name.span));
+                (name, value)
+            });
+
+        // struct ::= path generic_args? "{" (struct_field ("," struct_field)* ","?)? "}"
+        let struct_ = path_parser()
+            .then(generic_args.clone().or_not().map(Option::unwrap_or_default))
+            .then(
+                struct_field
+                    .separated_by(just(Token::Comma))
+                    .allow_trailing()
+                    .collect::<Vec<(Spanned<String>, Spanned<Expr>)>>()
+                    .delimited_by(just(Token::BraceOpen), just(Token::BraceClose))
+                    .recover_with(via_parser(recover_delimited(
+                        Token::BraceOpen,
+                        Token::BraceClose,
+                        |_| vec![],
+                    ))),
+            )
+            .validate(|((path, generic_args), fields), _, emitter| {
+                for (index, (name, _)) in fields.iter().enumerate() {
+                    let Some((first, _)) = fields[..index].iter().find(|(prev, _)| prev == name)
+                    else {
+                        continue;
+                    };
+                    emitter.emit(specified_twice("Field", name, first.span));
+                }
+                Expr::Struct(path, generic_args, fields)
+            })
+            .map_with(spanned)
+            .labelled("struct")
+            .boxed();
+
+        // tuple_field ::= (ident ":")? expr
+        let tuple_field = ident_parser()
+            .map_with(spanned)
+            .then_ignore(just(Token::Colon))
+            // `name: loop ..` is a labelled expression instead
+            .then_ignore(one_of([Token::Loop, Token::While, Token::For, Token::BraceOpen]).not())
+            .or_not()
+            .then(expr.clone());
+
+        // tuple ::= "(" (tuple_field ",")* tuple_field? ")"
+        let tuple = tuple_field
+            .clone()
+            .then_ignore(just(Token::Comma))
+            .repeated()
+            .collect::<Vec<(Option<Spanned<String>>, Spanned<Expr>)>>()
+            .then(tuple_field.clone().or_not())
+            .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+            .validate(|(mut fields, last), extra, emitter| {
+                let fields = match last {
+                    // Without a comma the parentheses only group
+                    Some((None, last)) if fields.is_empty() => return last,
+                    Some(last) => {
+                        fields.push(last);
+                        fields
+                    }
+                    None if fields.is_empty() => return spanned(Expr::Unit, extra),
+                    None => fields,
+                };
+                for (index, (name, _)) in fields.iter().enumerate() {
+                    let Some(name) = name else {
+                        continue;
+                    };
+                    let Some(first) = fields[..index]
+                        .iter()
+                        .find_map(|(prev, _)| prev.as_ref().filter(|prev| *prev == name))
+                    else {
+                        continue;
+                    };
+                    emitter.emit(specified_twice("Field", name, first.span));
+                }
+                spanned(Expr::Tuple(fields), extra)
+            })
+            .recover_with(via_parser(recover_delimited(
+                Token::ParenOpen,
+                Token::ParenClose,
+                |span| Spanned::new(Expr::Error, span),
+            )))
+            .labelled("tuple")
+            .boxed();
+
+        // atom ::= literal | struct | ident | array | tuple | block
         let atom = choice((
             literal,
-            ident_parser().map(String::from).map(Expr::Ident),
+            struct_,
+            ident_parser().map(Expr::Ident).map_with(spanned),
             array,
-            expr.clone()
-                .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
-            block,
+            tuple,
+            block.clone(),
         ))
         .labelled("atom");
 
-        let basic = {
-            use chumsky::pratt::{infix, left, postfix, prefix, right};
+        // argument ::= (ident ":")? expr
+        // arguments ::= "(" (argument ("," argument)*)? ")"
+        let arguments = tuple_field
+            .separated_by(just(Token::Comma))
+            .collect::<Vec<(Option<Spanned<String>>, Spanned<Expr>)>>()
+            .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+            .validate(|args, _, emitter| {
+                let mut positional = vec![];
+                let mut named: Vec<(Spanned<String>, Spanned<Expr>)> = vec![];
+                for (name, arg) in args {
+                    let Some(name) = name else {
+                        if let Some((first, _)) = named.first() {
+                            emitter.emit(ParseError::diagnostic(
+                                arg.span,
+                                Diagnostic::new(
+                                    Level::Error,
+                                    "Positional arguments must come before named arguments",
+                                )
+                                .with_child(arg.span, Level::Error, "Positional argument")
+                                .with_child(
+                                    first.span,
+                                    Level::Note,
+                                    "After this named argument",
+                                ),
+                            ));
+                        }
+                        positional.push(arg);
+                        continue;
+                    };
+                    if let Some((first, _)) = named.iter().find(|(prev, _)| *prev == name) {
+                        emitter.emit(specified_twice("Argument", &name, first.span));
+                        continue;
+                    }
+                    named.push((name, arg));
+                }
+                (positional, named)
+            })
+            .recover_with(via_parser(recover_delimited(
+                Token::ParenOpen,
+                Token::ParenClose,
+                |span| (vec![Spanned::new(Expr::Error, span)], vec![]),
+            )))
+            .boxed();
 
-            let binary = |associativity, token, op| {
-                infix(associativity, just(token), move |l, r| {
-                    Expr::Binary(op, Box::new(l), Box::new(r))
-                })
-            };
+        let call = {
+            use chumsky::pratt::postfix;
 
-            // https://doc.rust-lang.org/stable/reference/expressions.html#expression-precedence
+            // The operators are spanned, so that the whole expression can be spanned
             atom.clone().pratt((
-                // field ::= atom "." ident
+                // field ::= atom "." (ident | int)
                 postfix(
                     8,
-                    just(Token::Dot).ignore_then(ident_parser().map(String::from)),
-                    |l, field| Expr::FieldAccess(Box::new(l), field),
+                    just(Token::Dot).ignore_then(field_parser()),
+                    |l: Spanned<Expr>, fields: Vec<Spanned<String>>| {
+                        fields.into_iter().fold(l, |l, field| {
+                            let span = l.span.to(field.span);
+                            Spanned::new(Expr::FieldAccess(Box::new(l), field.val), span)
+                        })
+                    },
+                ),
+                // generic ::= atom generic_args
+                postfix(
+                    8,
+                    generic_args.map_with(spanned),
+                    |l: Spanned<Expr>, args: Spanned<Vec<Spanned<Type>>>| {
+                        let span = l.span.to(args.span);
+                        Spanned::new(Expr::Generic(Box::new(l), args.val), span)
+                    },
                 ),
                 // subscript ::= field "." "[" expr "]"
                 postfix(
                     7,
-                    just(Token::Dot).ignore_then(
-                        expr.clone()
-                            .delimited_by(just(Token::BracketOpen), just(Token::BracketClose)),
-                    ),
-                    |l, index| Expr::Subscript(Box::new(l), Box::new(index)),
+                    just(Token::Dot)
+                        .ignore_then(
+                            expr.clone()
+                                .delimited_by(just(Token::BracketOpen), just(Token::BracketClose)),
+                        )
+                        .map_with(spanned),
+                    |l: Spanned<Expr>, index: Spanned<Spanned<Expr>>| {
+                        let span = l.span.to(index.span);
+                        Spanned::new(Expr::Subscript(Box::new(l), Box::new(index.val)), span)
+                    },
                 ),
-                // call ::= field "(" (expr ("," expr)*)? ")"
+                // call ::= field arguments
                 postfix(
                     7,
-                    expr.clone()
-                        .separated_by(just(Token::Comma))
-                        .collect::<Vec<Expr>>()
-                        .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
-                    |l, args| Expr::Call(Box::new(l), args),
+                    arguments.map_with(spanned),
+                    |l: Spanned<Expr>, args: Spanned<Arguments>| {
+                        let span = l.span.to(args.span);
+                        let (args, named_args) = args.val;
+                        Spanned::new(Expr::Call(Box::new(l), args, named_args), span)
+                    },
                 ),
-                // unary ::= ("-" | "not") call
-                prefix(6, just(Token::Not), |r| {
-                    Expr::Unary(UnaryOp::Not, Box::new(r))
-                }),
-                prefix(6, just(Token::Minus), |r| {
-                    Expr::Unary(UnaryOp::Neg, Box::new(r))
-                }),
-                // mul_div ::= unary ("*" | "/") unary
-                binary(left(5), Token::Star, BinaryOp::Mul),
-                binary(left(5), Token::Slash, BinaryOp::Div),
+            ))
+        };
+
+        // Not part of the pratt parser below, because prefix operators don't know their span there
+        // unary ::= ("-" | "not")* call
+        let unary = choice((
+            just(Token::Not).to(UnaryOp::Not),
+            just(Token::Minus).to(UnaryOp::Neg),
+        ))
+        .map_with(spanned)
+        .repeated()
+        .collect::<Vec<_>>()
+        .then(call)
+        .validate(|(ops, expr), _, emitter| {
+            // Only integer literals which are negated directly may be one larger than their maximum
+            let negated = matches!(ops.last(), Some(op) if op.val == UnaryOp::Neg)
+                && matches!(expr.val, Expr::Literal(_));
+            let start = chain_start(&expr);
+            if let (false, Expr::Literal(Literal::Int(int, suffix))) = (negated, &start.val) {
+                if let Err(diagnostic) = check_int_range((*int).into(), *suffix, false, start.span)
+                {
+                    emitter.emit(ParseError::diagnostic(start.span, diagnostic));
+                }
+            }
+            (ops, expr)
+        })
+        .map(|(ops, expr)| {
+            ops.into_iter().rev().fold(expr, |r, op| {
+                let span = op.span.to(r.span);
+                Spanned::new(Expr::Unary(op.val, Box::new(r)), span)
+            })
+        })
+        .labelled("expression");
+
+        let basic = {
+            use chumsky::pratt::{infix, left, postfix, right};
+
+            let binary = |associativity, token, op| {
+                infix(
+                    associativity,
+                    just(token),
+                    move |l: Spanned<Expr>, r: Spanned<Expr>| {
+                        let span = l.span.to(r.span);
+                        Spanned::new(Expr::Binary(op, Box::new(l), Box::new(r)), span)
+                    },
+                )
+            };
+
+            // https://doc.rust-lang.org/stable/reference/expressions.html#expression-precedence
+            unary.clone().pratt((
+                // mul_div ::= unary ("*" | "/" | "mod") unary
+                binary(left(8), Token::Star, BinaryOp::Mul),
+                binary(left(8), Token::Slash, BinaryOp::Div),
+                binary(left(8), Token::Mod, BinaryOp::Mod),
                 // add_sub ::= mul_div ("+" | "-") mul_div
-                binary(left(4), Token::Plus, BinaryOp::Add),
-                binary(left(4), Token::Minus, BinaryOp::Sub),
+                binary(left(7), Token::Plus, BinaryOp::Add),
+                binary(left(7), Token::Minus, BinaryOp::Sub),
+                // concat ::= add_sub "++" add_sub
+                binary(left(6), Token::PlusPlus, BinaryOp::Concat),
                 // TODO Require parentheses
-                // compare ::= add_sub ("==" | "!=" | "<" | "<=" | ">" | ">=") add_sub
-                binary(left(3), Token::Eq, BinaryOp::Eq),
-                binary(left(3), Token::NotEq, BinaryOp::NotEq),
-                binary(left(3), Token::LessThan, BinaryOp::LessThan),
-                binary(left(3), Token::LessThanEq, BinaryOp::LessThanEq),
-                binary(left(3), Token::GreaterThan, BinaryOp::GreaterThan),
-                binary(left(3), Token::GreaterThanEq, BinaryOp::GreaterThanEq),
+                // compare ::= concat ("==" | "!=" | "<" | "<=" | ">" | ">=") concat
+                binary(left(5), Token::Eq, BinaryOp::Eq),
+                binary(left(5), Token::NotEq, BinaryOp::NotEq),
+                binary(left(5), Token::LessThan, BinaryOp::LessThan),
+                binary(left(5), Token::LessThanEq, BinaryOp::LessThanEq),
+                binary(left(5), Token::GreaterThan, BinaryOp::GreaterThan),
+                binary(left(5), Token::GreaterThanEq, BinaryOp::GreaterThanEq),
+                // and ::= compare "and" compare
+                binary(left(4), Token::And, BinaryOp::And),
+                // or ::= and "or" and
+                binary(left(3), Token::Or, BinaryOp::Or),
                 // TODO Require parentheses
                 // range ::= basic ".." basic
                 infix(
                     left(2),
                     just(Token::DotDot),
-                    |l, r| struct_expr! { "Range", start: l, end: r },
+                    |l: Spanned<Expr>, r: Spanned<Expr>| {
+                        let span = l.span.to(r.span);
+                        Spanned::new(struct_expr! { "Range", start: l, end: r }, span)
+                    },
+                ),
+                // range_inclusive ::= basic "..=" basic
+                infix(
+                    left(2),
+                    just(Token::DotDotEq),
+                    |l: Spanned<Expr>, r: Spanned<Expr>| {
+                        let span = l.span.to(r.span);
+                        Spanned::new(struct_expr! { "RangeInclusive", start: l, end: r }, span)
+                    },
                 ),
                 // range_from ::= basic ".."
                 postfix(
                     2,
-                    just(Token::DotDot),
-                    |l| struct_expr! { "RangeFrom", start: l },
-                ),
-                // range_to ::= ".." basic
-                prefix(
-                    2,
-                    just(Token::DotDot),
-                    |r| struct_expr! { "RangeTo", end: r },
+                    // Postfix operators are tried first, so leave `l..r` to the range above
+                    just(Token::DotDot)
+                        .then_ignore(unary.clone().not())
+                        .map_with(spanned),
+                    |l: Spanned<Expr>, op: Spanned<Token>| {
+                        let span = l.span.to(op.span);
+                        Spanned::new(struct_expr! { "RangeFrom", start: l }, span)
+                    },
                 ),
-                // assign ::= basic "=" basic
+                // assign ::= basic ("=" | "+=" | "-=" | "*=" | "/=") basic
                 binary(right(1), Token::Assign, BinaryOp::Assign),
-                // break ::= "break" basic
-                prefix(0, just(Token::Break), |r| Expr::Break(Box::new(r))),
-                // return_value ::= "return" basic
-                prefix(0, just(Token::Return), |r| Expr::Return(Box::new(r))),
+                binary(right(1), Token::PlusAssign, BinaryOp::AddAssign),
+                binary(right(1), Token::MinusAssign, BinaryOp::SubAssign),
+                binary(right(1), Token::StarAssign, BinaryOp::MulAssign),
+                binary(right(1), Token::SlashAssign, BinaryOp::DivAssign),
             ))
         };
 
+        // range_to ::= ".." basic
+        let range_to = just(Token::DotDot)
+            .ignore_then(basic.clone())
+            .map(|r| struct_expr! { "RangeTo", end: r })
+            .map_with(spanned);
+
+        // loop ::= "loop" expr
+        let r#loop = just(Token::Loop)
+            .ignore_then(expr.clone())
+            .map(|body| Expr::Loop(Box::new(body)))
+            .map_with(spanned)
+            .labelled("loop");
+
+        // while ::= "while" expr ":" expr
+        let r#while = just(Token::While)
+            .ignore_then(expr.clone())
+            .then_ignore(just(Token::Colon))
+            .then(expr.clone())
+            .map(|(cond, body)| Expr::While(Box::new(cond), Box::new(body)))
+            .map_with(spanned)
+            .labelled("while loop");
+
+        // for ::= "for" pattern "in" expr ":" expr
+        let r#for = just(Token::For)
+            .ignore_then(pattern_parser())
+            .then_ignore(just(Token::In))
+            .then(expr.clone())
+            .then_ignore(just(Token::Colon))
+            .then(expr.clone())
+            .map(|((pattern, iter), body)| Expr::For(pattern, Box::new(iter), Box::new(body)))
+            .map_with(spanned)
+            .labelled("for loop");
+
+        // labelled ::= ident ":" (loop | while | for | block)
+        let labelled = ident_parser()
+            .map_with(spanned)
+            .then_ignore(just(Token::Colon))
+            .then(choice((
+                r#loop.clone(),
+                r#while.clone(),
+                r#for.clone(),
+                block.clone(),
+            )))
+            .map(|(label, inner)| Expr::Label(label, Box::new(inner)))
+            .map_with(spanned)
+            .boxed();
+
+        // label ::= ":" ident
+        let label = just(Token::Colon).ignore_then(ident_parser().map_with(spanned));
+
+        // break ::= "break" label? expr?
+        let r#break = just(Token::Break)
+            .ignore_then(label.clone().or_not())
+            .then(expr.clone().or_not())
+            .map_with(|(label, r), extra| {
+                let r = r.unwrap_or_else(|| {
+                    let mut span = current_span(extra);
+                    span.start = span.end;
+                    Spanned::new(Expr::Unit, span)
+                });
+                Expr::Break(label, Box::new(r))
+            })
+            .map_with(spanned);
+
+        // continue ::= "continue" label?
+        let r#continue = just(Token::Continue)
+            .ignore_then(label.or_not())
+            .map(Expr::Continue)
+            .map_with(spanned);
+
         let if_else = just(Token::If)
             .ignore_then(expr.clone())
             .then_ignore(just(Token::Then))
             .then(expr.clone())
             .then(just(Token::Else).ignore_then(expr.clone()).or_not())
-            .map(|((cond, then_branch), else_branch)| {
-                Expr::IfElse(
-                    Box::new(cond),
-                    Box::new(then_branch),
-                    Box::new(else_branch.unwrap_or(Expr::Unit)),
-                )
+            .map_with(|((cond, then_branch), else_branch), extra| {
+                let else_branch = else_branch.unwrap_or_else(|| {
+                    let mut span = current_span(extra);
+                    span.start = span.end;
+                    Spanned::new(Expr::Unit, span)
+                });
+                Expr::IfElse(Box::new(cond), Box::new(then_branch), Box::new(else_branch))
             })
+            .map_with(spanned)
             .labelled("if else");
 
-        // return ::= "return"
-        let r#return = just(Token::Return).to(Expr::Return(Box::new(Expr::Unit)));
+        // arm ::= pattern ("if" expr)? "=>" expr
+        let arm = pattern_parser()
+            .then(just(Token::If).ignore_then(expr.clone()).or_not())
+            .then_ignore(just(Token::ThickArrow))
+            .then(expr.clone())
+            .map(|((pattern, guard), body)| (pattern, guard, body));
 
-        // expr ::= function | if_else | basic | atom
-        choice((function, if_else, basic, atom, r#return))
-            .boxed()
-            .labelled("expression")
+        // match ::= "match" expr "with" "|"? arm (","? "|" arm)* ","?
+        let r#match = just(Token::Match)
+            .ignore_then(expr.clone())
+            .then_ignore(just(Token::With))
+            .then_ignore(just(Token::Pipe).or_not())
+            .then(
+                arm.separated_by(just(Token::Comma).or_not().then(just(Token::Pipe)))
+                    .at_least(1)
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(just(Token::Comma).or_not())
+            .map(|(scrutinee, arms)| Expr::Match(Box::new(scrutinee), arms))
+            .map_with(spanned)
+            .labelled("match")
+            .boxed();
+
+        // return ::= "return" expr?
+        let r#return = just(Token::Return)
+            .ignore_then(expr.clone().or_not())
+            .map_with(|r, extra| {
+                let r = r.unwrap_or_else(|| {
+                    let mut span = current_span(extra);
+                    span.start = span.end;
+                    Spanned::new(Expr::Unit, span)
+                });
+                Expr::Return(Box::new(r))
+            })
+            .map_with(spanned);
+
+        // expr ::= function | if_else | match | loop | while | for | labelled | break | continue
+        //        | return | range_to | basic | atom
+        choice((
+            function, if_else, r#match, r#loop, r#while, r#for, labelled, r#break, r#continue,
+            r#return, range_to, basic, atom,
+        ))
+        .boxed()
+        .labelled("expression")
     })
 }
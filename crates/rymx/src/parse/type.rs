@@ -1,24 +1,47 @@
 use super::{common::*, error::ParseError};
-use crate::{ast::*, tokenize::Token};
+use crate::{ast::*, span::Spanned, tokenize::Token};
 use chumsky::{prelude::*, util::Maybe};
+use itertools::Itertools;
 
-pub fn type_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Type, Extra<'src>> + Clone {
+pub fn type_parser<'src>(
+) -> impl Parser<'src, TokenStream<'src>, Spanned<Type>, Extra<'src>> + Clone {
     recursive(|type_| {
         // literal ::= int | float | string
         let literal = literal_parser().map(Type::Literal);
 
-        let ident = ident_parser().map(String::from);
+        let ident = ident_parser();
 
-        // atom ::= "(" ")" | literal | path | "(" type ")"
+        // tuple_field ::= (ident ":")? type
+        let tuple_field = ident
+            .clone()
+            .then_ignore(just(Token::Colon))
+            .or_not()
+            .then(type_.clone());
+        // tuple ::= "(" (tuple_field ",")* tuple_field? ")"
+        let tuple = tuple_field
+            .clone()
+            .then_ignore(just(Token::Comma))
+            .repeated()
+            .collect::<Vec<(Option<String>, Spanned<Type>)>>()
+            .then(tuple_field.or_not())
+            .delimited_by(just(Token::ParenOpen), just(Token::ParenClose))
+            .map_with(|(mut fields, last), extra| match last {
+                // Without a comma the parentheses only group
+                Some((None, last)) if fields.is_empty() => last,
+                Some(last) => {
+                    fields.push(last);
+                    spanned(Type::Tuple(fields), extra)
+                }
+                None if fields.is_empty() => spanned(Type::Unit, extra),
+                None => spanned(Type::Tuple(fields), extra),
+            })
+            .labelled("tuple");
+
+        // atom ::= literal | path | tuple
         let atom = choice((
-            just(Token::ParenOpen)
-                .then(just(Token::ParenClose))
-                .to(Type::Unit),
-            literal,
-            path_parser().map(Type::Path),
-            type_
-                .clone()
-                .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
+            literal.map_with(spanned),
+            path_parser().map(Type::Path).map_with(spanned),
+            tuple,
         ))
         .labelled("atom");
 
@@ -29,34 +52,69 @@ pub fn type_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Type, Extra<'
                     .clone()
                     .separated_by(just(Token::Comma))
                     .allow_trailing()
-                    .collect::<Vec<Type>>()
+                    .collect::<Vec<Spanned<Type>>>()
                     .delimited_by(just(Token::BracketOpen), just(Token::BracketClose))
                     .or_not(),
             )
-            .map(|(typ, args)| match args {
-                Some(args) => Type::Generic(Box::new(typ), args),
+            .map_with(|(typ, args), extra| match args {
+                Some(args) => spanned(Type::Generic(Box::new(typ), args), extra),
                 None => typ,
             });
 
-        // struct_field ::= ident ":" type ("=" literal)?
-        let struct_field = ident
-            .clone()
+        // struct_field ::= doc_comment ident ":" type ("=" literal)?
+        let struct_field = doc_comment_parser()
+            .then(ident.clone())
             .then_ignore(just(Token::Colon))
             .then(type_.clone())
             .then(just(Token::Assign).ignore_then(literal_parser()).or_not())
-            .map(|((name, typ), default)| (name, typ, default));
+            .map(|(((doc, name), typ), default)| (name, typ, default, doc));
         // struct_fields ::= (struct_field ",")* struct_field?
         let struct_fields = struct_field
             .separated_by(just(Token::Comma))
             .allow_trailing()
-            .collect::<Vec<(String, Type, Option<Literal>)>>();
-        // struct ::= struct "{" struct_fields "}"
+            .collect::<Vec<(String, Spanned<Type>, Option<Literal>, Option<DocComment>)>>();
+        // struct ::= "struct" generic_params? "{" struct_fields "}"
         let struct_ = just(Token::Struct)
+            .ignore_then(generic_params_parser(type_.clone()).or_not())
+            .then(struct_fields.delimited_by(just(Token::BraceOpen), just(Token::BraceClose)))
+            .map(|(generics, fields)| Type::Struct(generics.unwrap_or_default(), fields))
+            .map_with(spanned)
+            .labelled("struct");
+
+        // function_param ::= ident ":" type "=" literal | type
+        let function_param = ident
+            .clone()
+            .then_ignore(just(Token::Colon))
+            .then(type_.clone())
+            .then_ignore(just(Token::Assign))
+            .then(literal_parser())
+            .map(|((name, typ), default)| Err((name, typ, default)))
+            .or(type_.clone().map(Ok));
+        // function ::= "fn" "(" (function_param ("," function_param)*)? ")" type?
+        let function = just(Token::Fn)
             .ignore_then(
-                struct_fields.delimited_by(just(Token::BraceOpen), just(Token::BraceClose)),
+                function_param
+                    .separated_by(just(Token::Comma))
+                    .allow_trailing()
+                    .collect::<Vec<Result<Spanned<Type>, (String, Spanned<Type>, Literal)>>>()
+                    .delimited_by(just(Token::ParenOpen), just(Token::ParenClose)),
             )
-            .map(Type::Struct)
-            .labelled("struct");
+            .then(type_.clone().or_not())
+            .map_with(|(params, return_type), extra| {
+                let (args, named_args) = params.into_iter().partition_result();
+                let return_type = return_type.unwrap_or_else(|| {
+                    let mut span = current_span(extra);
+                    span.start = span.end;
+                    Spanned::new(Type::Unit, span)
+                });
+                Type::Function {
+                    args,
+                    named_args,
+                    return_type: Box::new(return_type),
+                }
+            })
+            .map_with(spanned)
+            .labelled("function");
 
         // enum_variant ::= ident type?
         let enum_variant = ident.then(type_.clone().or_not());
@@ -64,11 +122,13 @@ pub fn type_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Type, Extra<'
         let enum_variants = enum_variant
             .separated_by(just(Token::Pipe))
             .allow_leading()
-            .collect::<Vec<(String, Option<Type>)>>();
-        // enum ::= "enum" enum_variants
+            .collect::<Vec<(String, Option<Spanned<Type>>)>>();
+        // enum ::= "enum" generic_params? enum_variants
         let enum_ = just(Token::Enum)
-            .ignore_then(enum_variants)
-            .map(Type::Enum)
+            .ignore_then(generic_params_parser(type_.clone()).or_not())
+            .then(enum_variants)
+            .map(|(generics, variants)| Type::Enum(generics.unwrap_or_default(), variants))
+            .map_with(spanned)
             .labelled("enum");
 
         // union_variants ::= "|"? type ("|" type)*
@@ -76,11 +136,12 @@ pub fn type_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Type, Extra<'
             .clone()
             .separated_by(just(Token::Pipe))
             .allow_leading()
-            .collect::<Vec<Type>>();
+            .collect::<Vec<Spanned<Type>>>();
         // union ::= "union" union_variants
         let union = just(Token::Union)
             .ignore_then(union_variants)
             .map(Type::Union)
+            .map_with(spanned)
             .labelled("union");
 
         // size ::= (path | int)
@@ -88,9 +149,10 @@ pub fn type_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Type, Extra<'
             .map(ArraySize::Path)
             .or(literal_parser().validate(|lit, extra, emitter| {
                 let found = match lit {
-                    Literal::Int(inner) => return ArraySize::Int(inner as u64),
+                    Literal::Int(inner, _) => return ArraySize::Int(inner),
                     Literal::Bool(_) => Token::Ident,
-                    Literal::Float(_) => Token::Float,
+                    Literal::Float(..) => Token::Float,
+                    Literal::Char(_) => Token::Char,
                     Literal::String(_) => Token::String,
                 };
                 emitter.emit(ParseError::expected_found(
@@ -106,9 +168,10 @@ pub fn type_parser<'src>() -> impl Parser<'src, TokenStream<'src>, Type, Extra<'
             .then(type_)
             .map(|(size, element)| {
                 Type::Array(size.unwrap_or(ArraySize::Unknown), Box::new(element))
-            });
+            })
+            .map_with(spanned);
 
-        choice((struct_, enum_, union, array, generic))
+        choice((struct_, enum_, union, function, array, generic))
     })
     .labelled("type")
 }
@@ -0,0 +1,188 @@
+use crate::{
+    ast::{Expr, Function, Literal, Module, Stmt},
+    error::{Diagnostic, Level},
+    span::{Span, Spanned},
+};
+
+/// Reports `break` and `continue` referring to labels that are not defined around them,
+/// and imports of files which were not loaded, because they are not module-level constants
+pub fn resolve_module(module: &Module) -> Vec<Diagnostic> {
+    let mut resolver = Resolver::default();
+    for (_, _, expr, _, _) in &module.constants {
+        resolver.expr(expr);
+    }
+    for item in &module.impls {
+        for (_, _, expr, _, _) in &item.constants {
+            resolver.expr(expr);
+        }
+    }
+    for test in &module.tests {
+        resolver.expr(&test.body);
+    }
+    for sub_module in &module.sub_modules {
+        resolver.diagnostics.extend(resolve_module(sub_module));
+    }
+    resolver.diagnostics
+}
+
+/// Same as [`resolve_module`], but for a single statement
+pub fn resolve_stmt(stmt: &Spanned<Stmt>) -> Vec<Diagnostic> {
+    let mut resolver = Resolver::default();
+    resolver.stmt(stmt);
+    resolver.diagnostics
+}
+
+#[derive(Default)]
+struct Resolver {
+    /// The labels around the current expression, and whether they belong to a loop
+    labels: Vec<(Spanned<String>, bool)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    fn stmt(&mut self, stmt: &Spanned<Stmt>) {
+        match &stmt.val {
+            Stmt::Expr(expr) | Stmt::Variable(_, _, _, expr, _) => self.expr(expr),
+            Stmt::Function(func) => self.function(func),
+            Stmt::Type(..) | Stmt::Error => (),
+        }
+    }
+
+    fn function(&mut self, func: &Function) {
+        // Labels can't be referred to across function boundaries
+        let labels = std::mem::take(&mut self.labels);
+        for (_, default) in func.named_params.values() {
+            self.expr(default);
+        }
+        self.expr(&func.body);
+        self.labels = labels;
+    }
+
+    fn expr(&mut self, expr: &Spanned<Expr>) {
+        match &expr.val {
+            Expr::Ident(name) if name == "import" => self.diagnostics.push(
+                Diagnostic::spanned(
+                    expr.span,
+                    Level::Error,
+                    "`import` can only be called directly",
+                )
+                .with_child(
+                    expr.span,
+                    Level::Error,
+                    "Files are imported while compiling",
+                ),
+            ),
+            Expr::Unit | Expr::Literal(_) | Expr::Ident(_) | Expr::Error => (),
+            Expr::Array(items) => items.iter().for_each(|item| self.expr(item)),
+            Expr::Struct(_, _, fields) => fields.iter().for_each(|(_, field)| self.expr(field)),
+            Expr::Tuple(fields) => fields.iter().for_each(|(_, field)| self.expr(field)),
+            Expr::Function(func) => self.function(func),
+            Expr::Module(module) => self.diagnostics.extend(resolve_module(module)),
+            Expr::Unary(_, rhs) | Expr::FieldAccess(rhs, _) | Expr::Loop(rhs) => self.expr(rhs),
+            Expr::Return(rhs) | Expr::Break(None, rhs) | Expr::Generic(rhs, _) => self.expr(rhs),
+            Expr::ArrayWithRepeat(lhs, rhs)
+            | Expr::Binary(_, lhs, rhs)
+            | Expr::Subscript(lhs, rhs)
+            | Expr::While(lhs, rhs)
+            | Expr::For(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Call(lhs, args, named_args) => {
+                match &lhs.val {
+                    Expr::Ident(name) if name == "import" => {
+                        self.import(expr.span, args, named_args)
+                    }
+                    _ => self.expr(lhs),
+                }
+                args.iter().for_each(|arg| self.expr(arg));
+                named_args.iter().for_each(|(_, arg)| self.expr(arg));
+            }
+            Expr::IfElse(cond, then, other) => {
+                self.expr(cond);
+                self.expr(then);
+                self.expr(other);
+            }
+            Expr::Block(stmts, final_expr) => {
+                stmts.iter().for_each(|stmt| self.stmt(stmt));
+                if let Some(final_expr) = final_expr {
+                    self.expr(final_expr);
+                }
+            }
+            Expr::Match(scrutinee, arms) => {
+                self.expr(scrutinee);
+                for (_, guard, body) in arms {
+                    if let Some(guard) = guard {
+                        self.expr(guard);
+                    }
+                    self.expr(body);
+                }
+            }
+            Expr::Label(label, inner) => {
+                let is_loop = matches!(inner.val, Expr::Loop(_) | Expr::While(..) | Expr::For(..));
+                self.labels.push((label.clone(), is_loop));
+                self.expr(inner);
+                self.labels.pop();
+            }
+            Expr::Break(Some(label), rhs) => {
+                self.target(label, false);
+                self.expr(rhs);
+            }
+            Expr::Continue(Some(label)) => self.target(label, true),
+            Expr::Continue(None) => (),
+        }
+    }
+
+    /// Files are loaded while compiling, so `import("std")` is the only import left at this point
+    fn import(
+        &mut self,
+        span: Span,
+        args: &[Spanned<Expr>],
+        named_args: &[(Spanned<String>, Spanned<Expr>)],
+    ) {
+        if let ([arg], []) = (args, named_args) {
+            if arg.val == Expr::Literal(Literal::String("std".into())) {
+                return;
+            }
+        }
+        self.diagnostics.push(
+            Diagnostic::spanned(
+                span,
+                Level::Error,
+                "Files can only be imported by module-level constants",
+            )
+            .with_child(span, Level::Error, "Not loaded while compiling")
+            .with_child(
+                vec![],
+                Level::Help,
+                "Use a constant in the module instead: `const name = import(\"file.rym\")`",
+            ),
+        );
+    }
+
+    /// Checks that the label of a `break` or `continue` is defined
+    fn target(&mut self, label: &Spanned<String>, is_continue: bool) {
+        match self
+            .labels
+            .iter()
+            .rev()
+            .find(|(name, _)| name.val == label.val)
+        {
+            None => self.diagnostics.push(Diagnostic::spanned(
+                label.span,
+                Level::Error,
+                format!("Label `{label}` is not defined"),
+            )),
+            Some((name, false)) if is_continue => self.diagnostics.push(
+                Diagnostic::spanned(
+                    label.span,
+                    Level::Error,
+                    format!("Unable to `continue` the block labelled `{label}`"),
+                )
+                .with_child(label.span, Level::Error, "Continued here")
+                .with_child(name.span, Level::Note, "Only loops can be continued"),
+            ),
+            Some(_) => (),
+        }
+    }
+}
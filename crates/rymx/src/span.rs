@@ -4,10 +4,37 @@ use std::fmt::{Debug, Display};
 
 type Index = usize;
 
-#[derive(Debug)]
+/// A node together with the location of the source code it was parsed from
+#[derive(Clone)]
 pub struct Spanned<T> {
-    val: T,
-    span: Span,
+    pub val: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(val: T, span: Span) -> Self {
+        Self { val, span }
+    }
+}
+
+/// Ignores the spans, so that nodes are compared structurally
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.val == other.val
+    }
+}
+
+/// Ignores the spans, otherwise printing the AST becomes unreadable
+impl<T: Debug> Debug for Spanned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.val.fmt(f)
+    }
+}
+
+impl<T: Display> Display for Spanned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.val.fmt(f)
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -37,6 +64,15 @@ impl Span {
         self.id = id;
         self
     }
+
+    /// Span from the start of `self` to the end of `other`
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            start: self.start,
+            end: other.end,
+            id: self.id,
+        }
+    }
 }
 
 impl From<Span> for Range<Index> {
@@ -1,6 +1,6 @@
-use crate::interpret::{NativeFunction, Value};
+use crate::interpret::{ModuleScope, NativeFunction, Value};
 
-pub const CONSTANTS: [(&str, Value); 7] = [
+pub const CONSTANTS: [(&str, Value); 9] = [
     ("true", Value::Bool(true)),
     ("false", Value::Bool(false)),
     (
@@ -17,6 +17,23 @@ pub const CONSTANTS: [(&str, Value); 7] = [
             Value::Unit
         }),
     ),
+    ("to_string", wrap_fn_1(|arg| Value::String(arg.to_string()))),
+    (
+        "import",
+        wrap_try_fn_1(|arg| match arg {
+            Value::String(name) if name == "std" => Ok(Value::Module(
+                name.clone(),
+                ModuleScope::with_constants(CONSTANTS),
+            )),
+            // Files are loaded while compiling, not by calls like `std.import(..)`
+            Value::String(name) => Err(format!(
+                "`{name}` can only be imported by a module-level constant"
+            )),
+            _ => Err(format!(
+                "Expected the name of a file to import, found '{arg}'"
+            )),
+        }),
+    ),
     (
         "read_to_string",
         wrap_fn_1(|arg| {
@@ -75,6 +92,10 @@ const fn wrap_fn_2(f: fn(&Value, &Value) -> Value) -> Value {
     Value::NativeFunction(NativeFunction::Params2(f))
 }
 
+const fn wrap_try_fn_1(f: fn(&Value) -> Result<Value, String>) -> Value {
+    Value::NativeFunction(NativeFunction::TryParams1(f))
+}
+
 const fn wrap_fn_var(f: fn(&[Value]) -> Value) -> Value {
     Value::NativeFunction(NativeFunction::ParamsVar(f))
 }
@@ -0,0 +1,117 @@
+//! Runs the `test "name" { .. }` blocks of a module, which are skipped when running a file
+
+use crate::{
+    ast::{Module, Test},
+    error::{Diagnostic, Level},
+    interpret::{self, ControlFlow},
+    Env,
+};
+use std::{
+    any::Any,
+    cell::Cell,
+    panic::AssertUnwindSafe,
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+thread_local! {
+    /// Whether this thread is running a test, whose panics are reported as failed tests
+    static IN_TEST: Cell<bool> = const { Cell::new(false) };
+}
+
+pub struct TestResult {
+    pub name: String,
+    /// Path of the sub module the test is declared in, empty for the root module
+    pub module: Vec<String>,
+    pub passed: bool,
+    pub duration: Duration,
+}
+
+/// Runs the tests of `module` and its sub modules, whose name contains `filter`.
+/// Every test gets a new environment from `new_env`, in which the items of the module are evaluated again.
+/// Failed tests are reported with a diagnostic.
+pub fn run_tests(mut module: Module, filter: &str, new_env: impl Fn() -> Env) -> Vec<TestResult> {
+    let mut tests = vec![];
+    take_tests(&mut module, &mut vec![], &mut tests);
+
+    tests
+        .into_iter()
+        .filter(|(_, test)| test.name.val.contains(filter))
+        .map(|(path, test)| run_test(&module, path, test, new_env()))
+        .collect()
+}
+
+/// Removes the tests of `module` and its sub modules, together with the path of their module
+fn take_tests(module: &mut Module, path: &mut Vec<String>, tests: &mut Vec<(Vec<String>, Test)>) {
+    for test in std::mem::take(&mut module.tests) {
+        tests.push((path.clone(), test));
+    }
+    for sub_module in &mut module.sub_modules {
+        path.push(sub_module.name.val.clone());
+        take_tests(sub_module, path, tests);
+        path.pop();
+    }
+}
+
+fn run_test(module: &Module, path: Vec<String>, test: Test, mut env: Env) -> TestResult {
+    let emitter = env.emitter.clone();
+    let body = test.body;
+    let start = Instant::now();
+    // Failed assertions panic, they are reported as failed tests instead of being printed
+    silence_test_panics();
+    IN_TEST.with(|in_test| in_test.set(true));
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        interpret::eval_test(&mut env, module.clone(), &path, body)
+    }));
+    IN_TEST.with(|in_test| in_test.set(false));
+    let duration = start.elapsed();
+
+    let failure = match result {
+        Ok(ControlFlow::Exit) => Some("Stopped because of an error".to_string()),
+        Ok(_) => None,
+        Err(payload) => Some(panic_message(payload)),
+    };
+    if let Some(reason) = &failure {
+        let name = &test.name;
+        Diagnostic::spanned(
+            name.span,
+            Level::Error,
+            format!("Test `{}` failed", name.val),
+        )
+        .with_child(name.span, Level::Error, reason)
+        .emit(emitter);
+    }
+
+    TestResult {
+        name: test.name.val,
+        module: path,
+        passed: failure.is_none(),
+        duration,
+    }
+}
+
+/// Installs a panic hook once, which ignores panics of running tests and passes all others
+/// to the previous hook. Swapping hooks for each test would race with tests on other threads.
+fn silence_test_panics() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !IN_TEST.with(Cell::get) {
+                hook(info);
+            }
+        }));
+    });
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = match payload.downcast_ref::<String>() {
+        Some(message) => message.as_str(),
+        None => payload
+            .downcast_ref::<&str>()
+            .copied()
+            .unwrap_or("Panicked"),
+    };
+    // Assertions repeat their values on the following lines
+    message.lines().next().unwrap_or_default().to_string()
+}
@@ -1,11 +1,104 @@
-use crate::Span;
+use crate::{
+    error::{Diagnostic, Level},
+    Span,
+};
 use logos::{Lexer, Logos};
 use std::fmt::Debug;
 
-pub fn tokenizer(src: &str) -> impl Iterator<Item = (Option<Token>, Span)> + '_ {
+pub fn tokenizer(src: &str) -> impl Iterator<Item = (Result<Token, TokenizeError>, Span)> + '_ {
     Token::lexer(src)
         .spanned()
-        .map(|(maybe_token, span)| (maybe_token.ok(), span.into()))
+        .map(|(result, span)| (result, span.into()))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TokenizeError {
+    #[default]
+    InvalidCharacter,
+    /// A block comment, which is missing at least one `*/`
+    UnterminatedBlockComment {
+        /// The outermost `/*`
+        opening: Span,
+        /// The innermost nested `/*`, which is still open
+        unclosed: Option<Span>,
+        /// The last `*/` together with the nested `/*` it closed
+        last_closing: Option<(Span, Span)>,
+    },
+    /// A string or character literal without its closing delimiter
+    UnterminatedLiteral {
+        kind: &'static str,
+        opening: Span,
+        terminator: String,
+    },
+}
+
+impl TokenizeError {
+    /// Creates a [`Diagnostic`] for this error, `span` has to point to the erroneous token
+    pub fn to_diagnostic(&self, span: Span, src: &str) -> Diagnostic {
+        let line = |span: &Span| src[..span.start].matches('\n').count() + 1;
+
+        match self {
+            TokenizeError::InvalidCharacter => Diagnostic::spanned(
+                span,
+                Level::Error,
+                format!("Invalid character `{}`", span.src(src)),
+            ),
+            TokenizeError::UnterminatedBlockComment {
+                opening,
+                unclosed,
+                last_closing,
+            } => {
+                let opening = opening.with_id(span.id);
+                let diagnostic =
+                    Diagnostic::spanned(opening, Level::Error, "Unterminated block comment")
+                        .with_child(opening, Level::Error, "This block comment is never closed");
+                let help = "Block comments can be nested, so every `/*` needs its own `*/`";
+
+                match (unclosed, last_closing) {
+                    (Some(unclosed), _) => diagnostic
+                        .with_child(
+                            unclosed.with_id(span.id),
+                            Level::Error,
+                            "This nested block comment is never closed as well",
+                        )
+                        .with_child(vec![], Level::Help, help),
+                    (None, Some((nested, closing))) => diagnostic
+                        .with_child(
+                            closing.with_id(span.id),
+                            Level::Error,
+                            format!(
+                                "This only closes the nested block comment from line {}",
+                                line(nested)
+                            ),
+                        )
+                        .with_child(vec![], Level::Help, help),
+                    (None, None) => diagnostic.with_child(
+                        vec![],
+                        Level::Help,
+                        "Add `*/` to close the block comment",
+                    ),
+                }
+            }
+            TokenizeError::UnterminatedLiteral {
+                kind,
+                opening,
+                terminator,
+            } => {
+                let opening = opening.with_id(span.id);
+                Diagnostic::spanned(opening, Level::Error, format!("Unterminated {kind}"))
+                    .with_child(
+                        opening,
+                        Level::Error,
+                        format!("This {kind} is never closed"),
+                    )
+                    .with_child(
+                        vec![],
+                        Level::Help,
+                        format!("Add `{terminator}` to close the {kind}"),
+                    )
+            }
+        }
+    }
 }
 
 fn line_comment(lexer: &mut Lexer<Token>) {
@@ -16,50 +109,138 @@ fn line_comment(lexer: &mut Lexer<Token>) {
     }
 }
 
-fn inline_comment(lexer: &mut Lexer<Token>) {
-    let mut nested = 1;
-    let mut chars = lexer.remainder().chars();
-    let mut offset = 0;
+fn inline_comment(lexer: &mut Lexer<Token>) -> Result<(), TokenizeError> {
+    let remainder = lexer.remainder();
+    let offset = lexer.span().end;
+    let mut openings = vec![lexer.span().start];
+    let mut last_closing = None;
+    let mut chars = remainder.char_indices();
 
-    while let Some(char) = chars.next() {
-        match char {
-            '*' if chars.next() == Some('/') => {
-                nested -= 1;
-                offset += 2;
-            }
-            '/' if chars.next() == Some('*') => {
-                nested += 1;
-                offset += 2;
+    while let Some((index, _)) = chars.next() {
+        if remainder[index..].starts_with("*/") {
+            chars.next();
+            let opening = openings.pop().expect("Internal Error: Missing `/*`");
+            if openings.is_empty() {
+                lexer.bump(index + 2);
+                return Ok(());
             }
-            _ => offset += 1,
+            last_closing = Some((
+                Span::new(opening, opening + 2),
+                Span::new(offset + index, offset + index + 2),
+            ));
+        } else if remainder[index..].starts_with("/*") {
+            chars.next();
+            openings.push(offset + index);
         }
-        if nested == 0 {
-            break;
+    }
+
+    let error = TokenizeError::UnterminatedBlockComment {
+        opening: Span::new(openings[0], openings[0] + 2),
+        unclosed: (openings.len() > 1).then(|| {
+            let unclosed = openings[openings.len() - 1];
+            Span::new(unclosed, unclosed + 2)
+        }),
+        last_closing,
+    };
+    // The comment was most likely supposed to end at the last `*/`
+    lexer.bump(last_closing.map_or(remainder.len(), |(_, closing)| closing.end - offset));
+    Err(error)
+}
+
+/// Returns the length of `src` up to and including the first `terminator`.
+/// Escaped characters are skipped if `escapes` is set.
+fn find_terminator(src: &str, terminator: &str, escapes: bool) -> Option<usize> {
+    let mut chars = src.char_indices();
+    while let Some((index, char)) = chars.next() {
+        if escapes && char == '\\' {
+            chars.next();
+        } else if src[index..].starts_with(terminator) {
+            return Some(index + terminator.len());
         }
     }
+    None
+}
+
+/// Bumps the lexer to the end of a literal, which started with the current token.
+fn literal(
+    lexer: &mut Lexer<Token>,
+    kind: &'static str,
+    terminator: &str,
+    escapes: bool,
+) -> Result<(), TokenizeError> {
+    let remainder = lexer.remainder();
+    if let Some(len) = find_terminator(remainder, terminator, escapes) {
+        lexer.bump(len);
+        return Ok(());
+    }
+
+    let error = TokenizeError::UnterminatedLiteral {
+        kind,
+        opening: lexer.span().into(),
+        terminator: terminator.to_string(),
+    };
+    // Continue with the next line to still find other errors
+    lexer.bump(remainder.find('\n').unwrap_or(remainder.len()));
+    Err(error)
+}
+
+fn char_literal(lexer: &mut Lexer<Token>) -> Result<(), TokenizeError> {
+    literal(lexer, "character literal", "'", true)
+}
 
-    lexer.bump(offset);
+fn string_literal(lexer: &mut Lexer<Token>) -> Result<(), TokenizeError> {
+    literal(lexer, "string literal", "\"", true)
+}
+
+fn raw_string(lexer: &mut Lexer<Token>) -> Result<(), TokenizeError> {
+    // The opening delimiter is `r`, followed by any number of `#` and a `"`
+    let pounds = lexer.slice().len() - 2;
+    literal(
+        lexer,
+        "string literal",
+        &format!("\"{}", "#".repeat(pounds)),
+        false,
+    )
+}
+
+fn multi_line_string(lexer: &mut Lexer<Token>) -> Result<(), TokenizeError> {
+    let result = literal(lexer, "string literal", r#"""""#, true);
+    if result.is_err() {
+        // The following lines are most likely part of the string as well
+        lexer.bump(lexer.remainder().len());
+    }
+    result
 }
 
 #[derive(
     logos_display::Display, Debug, Clone, Copy, Logos, PartialEq, Eq, PartialOrd, Ord, Hash,
 )]
+#[logos(error = TokenizeError)]
 pub enum Token {
     #[display_override("integer")]
-    #[regex(r"[0-9][0-9_]*")]
+    #[regex(r"[0-9][0-9_]*([iu](8|16|32|64|size))?")]
+    #[regex(r"0[xob][0-9a-zA-Z_]*")]
     Int,
     #[display_override("float")]
-    #[regex(r"[0-9][0-9_]*\.[0-9_]+")]
+    #[regex(r"[0-9][0-9_]*\.[0-9_]+([eE][+-]?[0-9_]*)?(f32|f64)?")]
+    #[regex(r"[0-9][0-9_]*[eE][+-]?[0-9_]*(f32|f64)?")]
+    #[regex(r"[0-9][0-9_]*(f32|f64)")]
     Float,
-    // #[display_override("character")]
-    #[regex(r#"'(\\'|[^'])*'"#)]
-    // Char,
+    #[display_override("character")]
+    #[token("'", char_literal)]
+    Char,
     #[display_override("string")]
-    #[regex(r#""(\\"|[^"])*""#)]
+    #[token("\"", string_literal)]
     String,
+    #[display_override("string")]
+    #[regex(r##"r#*""##, raw_string)]
+    RawString,
+    #[display_override("string")]
+    #[token(r#"""""#, multi_line_string)]
+    MultiLineString,
 
     #[display_override("identifier")]
-    #[regex("[a-zA-Z_][a-zA-Z0-9_]*")]
+    #[regex(r"[\p{XID_Start}_]\p{XID_Continue}*")]
     Ident,
 
     #[display_override("doc comment")]
@@ -77,12 +258,16 @@ pub enum Token {
     HSpace,
 
     // keywords
+    #[token("and")]
+    And,
     #[token("as")]
     As,
     #[token("break")]
     Break,
     #[token("const")]
     Const,
+    #[token("continue")]
+    Continue,
     #[token("else")]
     Else,
     #[token("enum")]
@@ -95,12 +280,26 @@ pub enum Token {
     If,
     #[token("impl")]
     Impl,
+    #[token("in")]
+    In,
     #[token("let")]
     Let,
+    #[token("loop")]
+    Loop,
+    #[token("match")]
+    Match,
+    #[token("mod")]
+    Mod,
+    #[token("module")]
+    Module,
     #[token("mut")]
     Mut,
     #[token("not")]
     Not,
+    #[token("or")]
+    Or,
+    #[token("pub")]
+    Pub,
     #[token("return")]
     Return,
     #[token("struct")]
@@ -113,6 +312,10 @@ pub enum Token {
     Union,
     #[token("use")]
     Use,
+    #[token("while")]
+    While,
+    #[token("with")]
+    With,
 
     #[token("{")]
     BraceOpen,
@@ -129,16 +332,30 @@ pub enum Token {
 
     #[token("&")]
     Ampersand,
+    #[token("@")]
+    At,
     #[token("=")]
     Assign,
+    #[token("+=")]
+    PlusAssign,
+    #[token("-=")]
+    MinusAssign,
+    #[token("*=")]
+    StarAssign,
+    #[token("/=")]
+    SlashAssign,
     #[token(",")]
     Comma,
     #[token(".")]
     Dot,
     #[token("..")]
     DotDot,
+    #[token("..=")]
+    DotDotEq,
     #[token("+")]
     Plus,
+    #[token("++")]
+    PlusPlus,
     #[token("|")]
     Pipe,
     #[token("-")]
@@ -180,7 +397,10 @@ impl Token {
         match self {
             Self::Int
             | Self::Float
+            | Self::Char
             | Self::String
+            | Self::RawString
+            | Self::MultiLineString
             | Self::Ident
             | Self::DocComment
             | Self::Comment
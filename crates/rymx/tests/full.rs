@@ -7,13 +7,14 @@ fn main() {
         let src = std::fs::read_to_string(path).unwrap();
         let mut out: Vec<u8> = vec![];
         let writer = strip_ansi_escapes::Writer::new(&mut out);
-        let (sender, mut emitter) = AriadneEmitter::new(writer);
+        let (sender, emitter) = AriadneEmitter::new(writer);
         let src_id = emitter.source_map.add(path.to_string_lossy(), &src);
+        let source_map = emitter.source_map.clone();
 
         std::thread::spawn(move || {
             let mut env = Env::new(sender.clone())
                 .with_constants(std_lib::CONSTANTS.into_iter().chain(std_lib::OTHER));
-            let module = rymx::compile_module(sender, &src, src_id)?;
+            let module = rymx::compile_module(sender, &source_map, src_id).ok()?;
             rymx::interpret(&mut env, module);
             Some(())
         });
@@ -22,3 +23,32 @@ fn main() {
         assert_snapshot!(String::from_utf8(out).unwrap());
     })
 }
+
+#[test]
+fn runner() {
+    insta::glob!("test_blocks.rym", |path| {
+        let src = std::fs::read_to_string(path).unwrap();
+        let mut out: Vec<u8> = vec![];
+        let writer = strip_ansi_escapes::Writer::new(&mut out);
+        let (sender, emitter) = AriadneEmitter::new(writer);
+        let emitter = emitter.without_debug();
+        let src_id = emitter.source_map.add(path.to_string_lossy(), &src);
+        let source_map = emitter.source_map.clone();
+
+        let runner = std::thread::spawn(move || {
+            let module = rymx::compile_module(sender.clone(), &source_map, src_id).unwrap();
+            let new_env = || Env::new(sender.clone()).with_constants(std_lib::CONSTANTS);
+            let mut results = rymx::testing::run_tests(module.clone(), "", new_env);
+            // Only runs the matching tests
+            results.extend(rymx::testing::run_tests(module, "square", new_env));
+            results
+                .into_iter()
+                .map(|result| format!("{:?} {:?} {}\n", result.module, result.name, result.passed))
+                .collect::<String>()
+        });
+
+        emitter.emit_all_blocking();
+        let results = runner.join().unwrap();
+        assert_snapshot!(String::from_utf8(out).unwrap() + &results);
+    })
+}